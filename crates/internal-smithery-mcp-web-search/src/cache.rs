@@ -0,0 +1,202 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// An in-memory cache whose entries expire after their own time-to-live.
+pub struct TtlCache<K, V> {
+    entries: Mutex<HashMap<K, (Instant, V)>>,
+}
+
+impl<K: Eq + Hash, V: Clone> TtlCache<K, V> {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().ok()?;
+
+        match entries.get(key) {
+            Some((expires_at, value)) if *expires_at > Instant::now() => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: K, value: V, ttl: Duration) {
+        if ttl.is_zero() {
+            return;
+        }
+
+        if let Ok(mut entries) = self.entries.lock() {
+            let now = Instant::now();
+
+            // Expired entries are dropped lazily, whenever something new is cached
+            entries.retain(|_, (expires_at, _)| *expires_at > now);
+            entries.insert(key, (now + ttl, value));
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> Default for TtlCache<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How long the origin allows a response to be cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// The origin says nothing; the configured TTL applies.
+    Default,
+    /// `no-store`, `no-cache` or `private`: never cache.
+    NoStore,
+    /// `s-maxage`, `max-age` or `Expires`.
+    MaxAge(Duration),
+}
+
+impl CachePolicy {
+    /// Reads `Cache-Control`, falling back to `Expires`.
+    pub fn from_headers(headers: &reqwest::header::HeaderMap) -> Self {
+        let cache_control = headers
+            .get_all(reqwest::header::CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|directive| directive.trim().to_ascii_lowercase())
+            .collect::<Vec<_>>();
+
+        if cache_control
+            .iter()
+            .any(|d| d == "no-store" || d == "no-cache" || d == "private")
+        {
+            return CachePolicy::NoStore;
+        }
+
+        // This cache is shared between clients, so `s-maxage` takes precedence
+        let max_age = |name: &str| {
+            cache_control.iter().find_map(|d| {
+                d.strip_prefix(name)?
+                    .trim_start()
+                    .strip_prefix('=')?
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+            })
+        };
+
+        if let Some(seconds) = max_age("s-maxage").or_else(|| max_age("max-age")) {
+            return CachePolicy::MaxAge(Duration::from_secs(seconds));
+        }
+
+        let date = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| httpdate::parse_http_date(value).ok())
+        };
+
+        if let Some(expires) = headers.get(reqwest::header::EXPIRES) {
+            // An invalid `Expires`, such as `0`, means already expired
+            let Some(expires) = date(reqwest::header::EXPIRES) else {
+                tracing::debug!("Invalid Expires header: {:?}", expires);
+                return CachePolicy::NoStore;
+            };
+
+            let now = date(reqwest::header::DATE).unwrap_or_else(SystemTime::now);
+
+            return CachePolicy::MaxAge(expires.duration_since(now).unwrap_or_default());
+        }
+
+        CachePolicy::Default
+    }
+
+    /// The lifetime of a cache entry, given the configured default TTL.
+    pub fn ttl(self, default: Duration) -> Duration {
+        match self {
+            CachePolicy::Default => default,
+            CachePolicy::NoStore => Duration::ZERO,
+            CachePolicy::MaxAge(max_age) => max_age,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(headers: &[(&'static str, &'static str)]) -> CachePolicy {
+        let mut map = reqwest::header::HeaderMap::new();
+
+        for &(name, value) in headers {
+            map.append(name, reqwest::header::HeaderValue::from_static(value));
+        }
+
+        CachePolicy::from_headers(&map)
+    }
+
+    #[test]
+    fn reads_cache_control() {
+        assert_eq!(policy(&[]), CachePolicy::Default);
+        assert_eq!(
+            policy(&[("cache-control", "public, max-age=60")]),
+            CachePolicy::MaxAge(Duration::from_secs(60))
+        );
+        assert_eq!(
+            policy(&[("cache-control", "max-age=60, s-maxage=600")]),
+            CachePolicy::MaxAge(Duration::from_secs(600))
+        );
+        assert_eq!(
+            policy(&[
+                ("cache-control", "max-age=60"),
+                ("cache-control", "No-Store")
+            ]),
+            CachePolicy::NoStore
+        );
+        assert_eq!(
+            policy(&[("cache-control", "private")]),
+            CachePolicy::NoStore
+        );
+    }
+
+    #[test]
+    fn falls_back_to_expires() {
+        assert_eq!(
+            policy(&[
+                ("date", "Wed, 21 Oct 2015 07:28:00 GMT"),
+                ("expires", "Wed, 21 Oct 2015 07:38:00 GMT"),
+            ]),
+            CachePolicy::MaxAge(Duration::from_secs(600))
+        );
+        assert_eq!(policy(&[("expires", "0")]), CachePolicy::NoStore);
+        assert_eq!(
+            policy(&[
+                ("cache-control", "max-age=5"),
+                ("expires", "Wed, 21 Oct 2015 07:38:00 GMT"),
+            ]),
+            CachePolicy::MaxAge(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn expires_entries() {
+        let cache = TtlCache::new();
+
+        cache.insert("kept", 1, Duration::from_secs(60));
+        cache.insert("never", 2, Duration::ZERO);
+
+        assert_eq!(cache.get(&"kept"), Some(1));
+        assert_eq!(cache.get(&"never"), None);
+        assert_eq!(
+            CachePolicy::Default.ttl(Duration::from_secs(300)),
+            Duration::from_secs(300)
+        );
+    }
+}
@@ -0,0 +1,732 @@
+use std::{net::SocketAddr, path::PathBuf, str::FromStr, sync::LazyLock, time::Duration};
+
+use serde::{Serialize, Serializer};
+
+use crate::{
+    retry::RetryPolicy,
+    tool::fetch::{MIN_CONTENT_LENGTH, Strategy},
+};
+
+/// Server-wide configuration, resolved once from environment variables.
+///
+/// Serializing it yields the diagnostic report served at `GET /config` when
+/// `EXPOSE_CONFIG` is set: secrets are reduced to a flag telling whether they
+/// are set.
+#[derive(Debug, Clone, Serialize)]
+pub struct Config {
+    /// `DISABLE_BROWSER`: never launch the headless browser.
+    /// The fetch tool then operates in reqwest-only mode.
+    pub disable_browser: bool,
+
+    /// `FETCH_BATCH_DEADLINE_SECS`: overall deadline for a `fetch` batch.
+    /// URLs not completed by then are reported as timed out.
+    #[serde(
+        rename = "fetch_batch_deadline_secs",
+        serialize_with = "as_optional_secs"
+    )]
+    pub fetch_batch_deadline: Option<Duration>,
+
+    /// `FETCH_FALLBACK_CHAIN`: the fetch strategies to try in order, until one
+    /// yields sufficient content, e.g. `reqwest,archive,browser,jina`
+    /// (default: `reqwest,browser`).
+    pub fetch_fallback_chain: Vec<Strategy>,
+
+    /// `FETCH_CACHE_TTL_SECS`: how long fetched pages are cached (default: 300).
+    /// `Cache-Control` and `Expires` from the origin take precedence; `0` disables the cache.
+    #[serde(rename = "fetch_cache_ttl_secs", serialize_with = "as_secs")]
+    pub fetch_cache_ttl: Duration,
+
+    /// `FETCH_MAX_CONCURRENCY`: calls of the tools fetching pages (`fetch`,
+    /// `snapshot`, `links`, `screenshot`...) running at once; further calls
+    /// wait for a slot (default: 8).
+    pub fetch_max_concurrency: usize,
+
+    /// `FETCH_URL_CONCURRENCY`: plain HTTP requests in flight at once within a
+    /// `fetch` call (default: 4). Browser fallbacks always run one at a time.
+    pub fetch_url_concurrency: usize,
+
+    /// `SEARCH_MAX_CONCURRENCY`: `search` calls running at once, budgeted
+    /// independently of `fetch` (default: 16).
+    pub search_max_concurrency: usize,
+
+    /// `SEARCH_CACHE_TTL_SECS`: how long search results are cached, keyed by
+    /// the normalized query and filters (default: 300). `0` disables the cache.
+    #[serde(rename = "search_cache_ttl_secs", serialize_with = "as_secs")]
+    pub search_cache_ttl: Duration,
+
+    /// `SEARCH_SYNONYMS_FILE`: synonyms used by `expand_query`, one
+    /// `term: synonym, synonym` entry per line. A small built-in list is used
+    /// when unset.
+    pub search_synonyms: Vec<(String, Vec<String>)>,
+
+    /// `FETCH_ALLOW_PRIVATE_IPS`: allow fetching loopback, private and
+    /// link-local addresses, for trusted internal deployments.
+    pub fetch_allow_private_ips: bool,
+
+    /// `FETCH_MIN_CONTENT_LENGTH`: characters a page fetched without the
+    /// browser must contain to be considered sufficient (default: 300).
+    pub fetch_min_content_length: usize,
+
+    /// `FETCH_MAX_RETRY_AFTER_SECS`: longest `Retry-After` honored when a site
+    /// answers 429 (default: 10). Longer waits are reported as rate limited.
+    #[serde(rename = "fetch_max_retry_after_secs", serialize_with = "as_secs")]
+    pub fetch_max_retry_after: Duration,
+
+    /// `FETCH_MAX_BYTES`: maximum number of body bytes read per page
+    /// (default: 5 MiB). The rest of the body is never downloaded.
+    pub fetch_max_bytes: usize,
+
+    /// `FETCH_MAX_TOTAL_BYTES`: maximum size of the serialized results of a
+    /// `fetch` batch. Once it is reached, the result at the limit is cut and
+    /// the later ones are dropped, each with a note (default: unlimited).
+    pub fetch_max_total_bytes: Option<usize>,
+
+    /// `FETCH_ALLOWED_CONTENT_TYPES`: comma-separated media types processed by
+    /// `fetch`, where `*` matches anything, e.g. `text/*,application/json`.
+    /// Responses of other types are rejected before any conversion
+    /// (default: text, JSON, XML, gzip and PDF). Allowed binary types, such
+    /// as `application/pdf`, are reported as downloads rather than converted.
+    pub fetch_allowed_content_types: Vec<String>,
+
+    /// `FETCH_CONVERT_ATTACHMENTS`: convert responses sent with
+    /// `Content-Disposition: attachment` like any other, instead of reporting
+    /// them as downloadable files (default: `false`).
+    pub fetch_convert_attachments: bool,
+
+    /// `CONVERT_MAX_CONCURRENCY`: maximum number of pages converted to
+    /// markdown at once, off the async runtime (default: the number of CPUs).
+    pub convert_max_concurrency: usize,
+
+    /// `BROWSER_WORKER_THREADS`: size of the thread pool dedicated to browser
+    /// operations (default: 4).
+    pub browser_worker_threads: usize,
+
+    /// `BROWSER_QUEUE_TIMEOUT_SECS`: how long a browser operation may wait for
+    /// a free worker before failing as busy (default: 30).
+    #[serde(rename = "browser_queue_timeout_secs", serialize_with = "as_secs")]
+    pub browser_queue_timeout: Duration,
+
+    /// `SHUTDOWN_DRAIN_SECS`: how long in-flight requests may keep running
+    /// after a shutdown signal before the server exits anyway (default: 30).
+    #[serde(rename = "shutdown_drain_secs", serialize_with = "as_secs")]
+    pub shutdown_drain: Duration,
+
+    /// `FETCH_DNS_TIMEOUT_SECS`: how long resolving a host may take before
+    /// the request fails, so that a broken DNS doesn't hang a fetch (default: 5).
+    #[serde(rename = "fetch_dns_timeout_secs", serialize_with = "as_secs")]
+    pub fetch_dns_timeout: Duration,
+
+    /// `FETCH_ACCEPT_LANGUAGE`: the `Accept-Language` sent by both reqwest
+    /// and the browser, e.g. `ja,en;q=0.8`, so that localized sites serve
+    /// the same content to either (default: not sent).
+    pub fetch_accept_language: Option<String>,
+
+    /// `User-Agent` overrides per host, read from the file at
+    /// `FETCH_USER_AGENTS_FILE` (one `host=user agent` rule per line) and from
+    /// `FETCH_USER_AGENTS` (rules separated by `|`). A host pattern is either
+    /// a host, or `*.example.com` for a domain and its subdomains. The first
+    /// matching rule wins.
+    pub user_agents: Vec<UserAgentRule>,
+
+    /// Retries of failed requests, shared by fetch and search.
+    pub retry: RetryPolicy,
+
+    /// `CHROME_PATH`: the headless Chrome binary used for the browser
+    /// fallback (default: detected by `headless_chrome`, which honors `CHROME`
+    /// and looks for Chrome and Chromium in the usual places).
+    pub chrome_path: Option<PathBuf>,
+
+    /// `EXPOSE_CONFIG`: serve this configuration at `GET /config`
+    /// (default: `false`). The endpoint is unauthenticated and reveals how
+    /// the server is set up, so only enable it where the port is not public.
+    pub expose_config: bool,
+
+    /// `EXA_API_KEY`: used by `search` when the client doesn't pass `exa_api_key`.
+    #[serde(rename = "exa_api_key_set", serialize_with = "is_set")]
+    pub exa_api_key: Option<String>,
+
+    /// `EXA_API_URL`: the base URL of the Exa API, e.g. for a proxy
+    /// (default: `https://api.exa.ai`).
+    pub exa_api_url: String,
+}
+
+impl Config {
+    fn from_env() -> Self {
+        Self {
+            disable_browser: env_flag("DISABLE_BROWSER"),
+            fetch_batch_deadline: env_parse("FETCH_BATCH_DEADLINE_SECS").map(Duration::from_secs),
+            fetch_fallback_chain: env_list("FETCH_FALLBACK_CHAIN")
+                .filter(|chain| !chain.is_empty())
+                .unwrap_or_else(|| vec![Strategy::Reqwest, Strategy::Browser]),
+            fetch_cache_ttl: Duration::from_secs(env_parse("FETCH_CACHE_TTL_SECS").unwrap_or(300)),
+            fetch_max_concurrency: env_parse("FETCH_MAX_CONCURRENCY")
+                .filter(|&concurrency| concurrency > 0)
+                .unwrap_or(8),
+            fetch_url_concurrency: env_parse("FETCH_URL_CONCURRENCY")
+                .filter(|&concurrency| concurrency > 0)
+                .unwrap_or(4),
+            search_max_concurrency: env_parse("SEARCH_MAX_CONCURRENCY")
+                .filter(|&concurrency| concurrency > 0)
+                .unwrap_or(16),
+            search_cache_ttl: Duration::from_secs(
+                env_parse("SEARCH_CACHE_TTL_SECS").unwrap_or(300),
+            ),
+            search_synonyms: search_synonyms_from_env(),
+            fetch_allow_private_ips: env_flag("FETCH_ALLOW_PRIVATE_IPS"),
+            fetch_min_content_length: fetch_min_content_length_from_env(),
+            fetch_max_retry_after: Duration::from_secs(
+                env_parse("FETCH_MAX_RETRY_AFTER_SECS").unwrap_or(10),
+            ),
+            fetch_max_bytes: env_parse("FETCH_MAX_BYTES").unwrap_or(5 * 1024 * 1024),
+            fetch_max_total_bytes: env_parse("FETCH_MAX_TOTAL_BYTES"),
+            fetch_allowed_content_types: env_list("FETCH_ALLOWED_CONTENT_TYPES")
+                .filter(|types| !types.is_empty())
+                .unwrap_or_else(|| {
+                    [
+                        "text/*",
+                        "application/json",
+                        "application/*+json",
+                        "application/xml",
+                        "application/*+xml",
+                        "application/gzip",
+                        "application/x-gzip",
+                        "application/pdf",
+                    ]
+                    .map(str::to_owned)
+                    .to_vec()
+                }),
+            fetch_convert_attachments: env_flag("FETCH_CONVERT_ATTACHMENTS"),
+            convert_max_concurrency: env_parse("CONVERT_MAX_CONCURRENCY")
+                .filter(|&concurrency| concurrency > 0)
+                .unwrap_or_else(|| {
+                    std::thread::available_parallelism().map_or(4, |threads| threads.get())
+                }),
+            browser_worker_threads: env_parse("BROWSER_WORKER_THREADS")
+                .filter(|&threads| threads > 0)
+                .unwrap_or(4),
+            browser_queue_timeout: Duration::from_secs(
+                env_parse("BROWSER_QUEUE_TIMEOUT_SECS").unwrap_or(30),
+            ),
+            shutdown_drain: Duration::from_secs(env_parse("SHUTDOWN_DRAIN_SECS").unwrap_or(30)),
+            fetch_dns_timeout: Duration::from_secs(
+                env_parse("FETCH_DNS_TIMEOUT_SECS")
+                    .filter(|&secs| secs > 0)
+                    .unwrap_or(5),
+            ),
+            fetch_accept_language: std::env::var("FETCH_ACCEPT_LANGUAGE")
+                .ok()
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty()),
+            user_agents: user_agents_from_env(),
+            retry: retry_policy_from_env(),
+            chrome_path: std::env::var_os("CHROME_PATH")
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+            expose_config: env_flag("EXPOSE_CONFIG"),
+            exa_api_key: std::env::var("EXA_API_KEY")
+                .ok()
+                .filter(|key| !key.is_empty()),
+            exa_api_url: std::env::var("EXA_API_URL")
+                .ok()
+                .map(|url| url.trim().trim_end_matches('/').to_owned())
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(|| "https://api.exa.ai".to_owned()),
+        }
+    }
+}
+
+impl Config {
+    /// The Chrome binary to launch: `CHROME_PATH` when set, provided it
+    /// exists, or else the one `headless_chrome` detects.
+    pub fn chrome_executable(&self) -> Result<PathBuf, String> {
+        match &self.chrome_path {
+            Some(path) if path.exists() => Ok(path.clone()),
+            Some(path) => Err(format!("CHROME_PATH {} does not exist", path.display())),
+            None => headless_chrome::browser::default_executable().map_err(|e| {
+                format!(
+                    "No Chrome binary found ({}): set CHROME_PATH, or DISABLE_BROWSER=true to run without the browser",
+                    e
+                )
+            }),
+        }
+    }
+
+    /// The problems of settings that are valid on their own, but leave part
+    /// of the server unusable.
+    fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+
+        if !self.disable_browser
+            && let Err(e) = self.chrome_executable()
+        {
+            problems.push(e);
+        }
+
+        if self.exa_api_key.is_none() {
+            problems.push(
+                "EXA_API_KEY is not set: search only works for clients passing `exa_api_key`"
+                    .to_owned(),
+            );
+        }
+
+        problems
+    }
+
+    /// The `User-Agent` configured for the host of `url`, if any.
+    pub fn user_agent_for(&self, url: &str) -> Option<&str> {
+        let url = reqwest::Url::parse(url).ok()?;
+        let host = url.host_str()?.to_ascii_lowercase();
+
+        self.user_agents
+            .iter()
+            .find(|rule| rule.matches(&host))
+            .map(|rule| rule.user_agent.as_str())
+    }
+}
+
+/// A `User-Agent` sent to the hosts matching a pattern, e.g. for sites that
+/// serve clean content to specific crawlers.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserAgentRule {
+    pub host: String,
+    pub user_agent: String,
+}
+
+impl UserAgentRule {
+    /// Parses a `host=user agent` rule.
+    fn parse(rule: &str) -> Option<Self> {
+        let (host, user_agent) = rule.split_once('=')?;
+        let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+        let user_agent = user_agent.trim();
+
+        (!host.is_empty() && !user_agent.is_empty()).then(|| Self {
+            host,
+            user_agent: user_agent.to_owned(),
+        })
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        match self.host.strip_prefix("*.") {
+            Some(domain) => {
+                host == domain
+                    || host
+                        .strip_suffix(domain)
+                        .is_some_and(|subdomain| subdomain.ends_with('.'))
+            }
+            None => host == self.host,
+        }
+    }
+}
+
+const DEFAULT_BIND_ADDR: &str = "0.0.0.0:8081";
+
+/// The address the server listens on: `BIND_ADDR` (default: `0.0.0.0:8081`),
+/// with its port replaced by `PORT` when set, as platforms often inject it.
+pub fn bind_addr() -> Result<SocketAddr, String> {
+    resolve_bind_addr(
+        std::env::var("BIND_ADDR").ok().as_deref(),
+        std::env::var("PORT").ok().as_deref(),
+    )
+}
+
+fn resolve_bind_addr(bind_addr: Option<&str>, port: Option<&str>) -> Result<SocketAddr, String> {
+    let bind_addr = bind_addr
+        .map(str::trim)
+        .filter(|bind_addr| !bind_addr.is_empty())
+        .unwrap_or(DEFAULT_BIND_ADDR);
+
+    let mut address: SocketAddr = bind_addr.parse().map_err(|e| {
+        format!(
+            "Invalid BIND_ADDR {:?}: {} (expected an IP address and port, e.g. {})",
+            bind_addr, e, DEFAULT_BIND_ADDR
+        )
+    })?;
+
+    if let Some(port) = port.map(str::trim).filter(|port| !port.is_empty()) {
+        let port = port
+            .parse()
+            .map_err(|e| format!("Invalid PORT {:?}: {}", port, e))?;
+        address.set_port(port);
+    }
+
+    Ok(address)
+}
+
+fn user_agents_from_env() -> Vec<UserAgentRule> {
+    let file = std::env::var("FETCH_USER_AGENTS_FILE")
+        .ok()
+        .and_then(|path| match std::fs::read_to_string(&path) {
+            Ok(content) => Some(content),
+            Err(e) => {
+                ignored(format!("Ignoring FETCH_USER_AGENTS_FILE {:?}: {}", path, e));
+                None
+            }
+        })
+        .unwrap_or_default();
+
+    let env = std::env::var("FETCH_USER_AGENTS").unwrap_or_default();
+
+    file.lines()
+        .chain(env.split('|'))
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty() && !rule.starts_with('#'))
+        .filter_map(|rule| {
+            let parsed = UserAgentRule::parse(rule);
+            if parsed.is_none() {
+                ignored(format!("Ignoring invalid User-Agent rule: {:?}", rule));
+            }
+            parsed
+        })
+        .collect()
+}
+
+fn search_synonyms_from_env() -> Vec<(String, Vec<String>)> {
+    let Ok(path) = std::env::var("SEARCH_SYNONYMS_FILE") else {
+        return vec![];
+    };
+
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            ignored(format!("Ignoring SEARCH_SYNONYMS_FILE {:?}: {}", path, e));
+            return vec![];
+        }
+    };
+
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let Some((term, synonyms)) = line.split_once(':') else {
+                ignored(format!("Ignoring invalid synonym entry: {:?}", line));
+                return None;
+            };
+
+            let synonyms = synonyms
+                .split(',')
+                .map(str::trim)
+                .filter(|synonym| !synonym.is_empty())
+                .map(str::to_owned)
+                .collect();
+
+            Some((term.trim().to_lowercase(), synonyms))
+        })
+        .collect()
+}
+
+fn retry_policy_from_env() -> RetryPolicy {
+    let default = RetryPolicy::default();
+
+    RetryPolicy {
+        max_attempts: env_parse("RETRY_MAX_ATTEMPTS")
+            .filter(|&attempts| attempts > 0)
+            .unwrap_or(default.max_attempts),
+        base_delay: env_parse("RETRY_BASE_DELAY_MS")
+            .map(Duration::from_millis)
+            .unwrap_or(default.base_delay),
+        max_delay: env_parse("RETRY_MAX_DELAY_MS")
+            .map(Duration::from_millis)
+            .unwrap_or(default.max_delay),
+        jitter: std::env::var("RETRY_JITTER")
+            .map(|_| env_flag("RETRY_JITTER"))
+            .unwrap_or(default.jitter),
+    }
+}
+
+fn fetch_min_content_length_from_env() -> usize {
+    let length = env_parse("FETCH_MIN_CONTENT_LENGTH").unwrap_or(MIN_CONTENT_LENGTH);
+
+    if length == 0 || length > 100_000 {
+        tracing::warn!(
+            "FETCH_MIN_CONTENT_LENGTH={} looks suspicious: pages will {}",
+            length,
+            if length == 0 {
+                "never fall back to the browser"
+            } else {
+                "almost always fall back to the browser"
+            }
+        );
+    }
+
+    length
+}
+
+static CONFIG: LazyLock<Config> = LazyLock::new(Config::from_env);
+
+pub fn config() -> &'static Config {
+    &CONFIG
+}
+
+/// The settings ignored while reading the configuration, reported by
+/// `--check-config`.
+static IGNORED: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+
+/// Logs a setting ignored because it is invalid, and records it.
+fn ignored(message: String) {
+    tracing::warn!("{}", message);
+
+    if let Ok(mut ignored) = IGNORED.lock() {
+        ignored.push(message);
+    }
+}
+
+/// Validates the configuration for `--check-config`, returning its problems:
+/// ignored settings, an unparsable listen address, a missing Chrome binary
+/// and a missing Exa API key.
+pub fn check() -> Vec<String> {
+    let config = config();
+
+    let mut problems = IGNORED
+        .lock()
+        .map(|ignored| ignored.clone())
+        .unwrap_or_default();
+
+    if let Err(e) = bind_addr() {
+        problems.push(e);
+    }
+
+    problems.extend(config.problems());
+
+    problems
+}
+
+/// Reads a boolean flag. `1`, `true`, `yes` and `on` are truthy.
+fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|value| {
+            matches!(
+                value.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false)
+}
+
+/// Parses a variable, ignoring it with a warning when the value is invalid.
+fn env_parse<T: FromStr>(name: &str) -> Option<T> {
+    let value = std::env::var(name).ok()?;
+
+    match value.trim().parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            ignored(format!("Ignoring invalid value for {}: {:?}", name, value));
+            None
+        }
+    }
+}
+
+/// Parses a comma-separated list, skipping invalid items with a warning.
+fn env_list<T: FromStr>(name: &str) -> Option<Vec<T>> {
+    Some(parse_list(name, &std::env::var(name).ok()?))
+}
+
+/// Parses a comma-separated list, in order, skipping the invalid items.
+fn parse_list<T: FromStr>(name: &str, value: &str) -> Vec<T> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .filter_map(|item| match item.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                ignored(format!("Ignoring invalid item in {}: {:?}", name, item));
+                None
+            }
+        })
+        .collect()
+}
+
+fn as_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    duration.as_secs().serialize(serializer)
+}
+
+fn as_optional_secs<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    duration.map(|d| d.as_secs()).serialize(serializer)
+}
+
+fn is_set<S: Serializer>(secret: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    secret.is_some().serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_fallback_chain_order() {
+        let chain = parse_list::<Strategy>("FETCH_FALLBACK_CHAIN", "jina, Archive,,bogus,reqwest");
+
+        assert_eq!(
+            chain,
+            [Strategy::Jina, Strategy::Archive, Strategy::Reqwest]
+        );
+    }
+
+    #[test]
+    fn matches_user_agent_rules_by_host() {
+        let config = Config {
+            user_agents: [
+                "news.example.com=Reader/1",
+                "*.Example.com.=Crawler/2",
+                "bogus",
+            ]
+            .into_iter()
+            .filter_map(UserAgentRule::parse)
+            .collect(),
+            ..config().clone()
+        };
+
+        assert_eq!(config.user_agents.len(), 2);
+
+        // The first matching rule wins
+        assert_eq!(
+            config.user_agent_for("https://news.example.com/a"),
+            Some("Reader/1")
+        );
+        assert_eq!(
+            config.user_agent_for("https://EXAMPLE.com/"),
+            Some("Crawler/2")
+        );
+        assert_eq!(
+            config.user_agent_for("http://docs.example.com:8080/"),
+            Some("Crawler/2")
+        );
+        assert_eq!(config.user_agent_for("https://notexample.com/"), None);
+        assert_eq!(config.user_agent_for("not a url"), None);
+    }
+
+    #[test]
+    fn resolves_bind_addr() {
+        assert_eq!(
+            resolve_bind_addr(None, None).unwrap(),
+            "0.0.0.0:8081".parse().unwrap()
+        );
+        assert_eq!(
+            resolve_bind_addr(Some("127.0.0.1:9000"), None).unwrap(),
+            "127.0.0.1:9000".parse().unwrap()
+        );
+        assert_eq!(
+            resolve_bind_addr(Some(" "), Some("3000")).unwrap(),
+            "0.0.0.0:3000".parse().unwrap()
+        );
+        // `PORT` overrides the port of `BIND_ADDR`
+        assert_eq!(
+            resolve_bind_addr(Some("[::1]:9000"), Some(" 3000 ")).unwrap(),
+            "[::1]:3000".parse().unwrap()
+        );
+        assert_eq!(
+            resolve_bind_addr(Some("127.0.0.1:9000"), Some("")).unwrap(),
+            "127.0.0.1:9000".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_bind_addr() {
+        assert!(
+            resolve_bind_addr(Some("localhost:8081"), None)
+                .unwrap_err()
+                .starts_with("Invalid BIND_ADDR")
+        );
+        assert!(
+            resolve_bind_addr(None, Some("80000"))
+                .unwrap_err()
+                .starts_with("Invalid PORT")
+        );
+    }
+
+    #[test]
+    fn reports_unusable_settings() {
+        let config = Config {
+            disable_browser: false,
+            chrome_path: Some(PathBuf::from("/nonexistent/chrome")),
+            exa_api_key: None,
+            ..config().clone()
+        };
+
+        let problems = config.problems();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("CHROME_PATH /nonexistent/chrome does not exist"));
+        assert!(problems[1].starts_with("EXA_API_KEY is not set"));
+
+        let config = Config {
+            disable_browser: true,
+            exa_api_key: Some("key".to_owned()),
+            ..config
+        };
+
+        assert!(config.problems().is_empty());
+    }
+
+    #[test]
+    fn redacts_secrets() {
+        let config = Config {
+            exa_api_key: Some("exa-secret-key".to_owned()),
+            chrome_path: Some(PathBuf::from("/opt/chrome/chrome")),
+            fetch_max_concurrency: 3,
+            ..config().clone()
+        };
+
+        let exposed = serde_json::to_value(&config).unwrap();
+
+        assert!(!exposed.to_string().contains("exa-secret-key"));
+        assert!(exposed.get("exa_api_key").is_none());
+        assert_eq!(exposed["exa_api_key_set"], true);
+
+        assert_eq!(exposed["chrome_path"], "/opt/chrome/chrome");
+        assert_eq!(exposed["fetch_max_concurrency"], 3);
+
+        let exposed = serde_json::to_value(Config {
+            exa_api_key: None,
+            ..config
+        })
+        .unwrap();
+
+        assert_eq!(exposed["exa_api_key_set"], false);
+    }
+
+    #[test]
+    fn records_ignored_items() {
+        parse_list::<Strategy>("FETCH_FALLBACK_CHAIN", "reqwest,teleport");
+
+        assert!(IGNORED.lock().unwrap().iter().any(|message| {
+            message == "Ignoring invalid item in FETCH_FALLBACK_CHAIN: \"teleport\""
+        }));
+    }
+
+    #[test]
+    fn uses_chrome_path_when_it_exists() {
+        let existing = std::env::current_exe().unwrap();
+
+        let config = Config {
+            chrome_path: Some(existing.clone()),
+            ..config().clone()
+        };
+        assert_eq!(config.chrome_executable(), Ok(existing));
+
+        let config = Config {
+            chrome_path: Some(PathBuf::from("/nonexistent/chrome")),
+            ..config
+        };
+        assert_eq!(
+            config.chrome_executable(),
+            Err("CHROME_PATH /nonexistent/chrome does not exist".to_owned())
+        );
+    }
+
+    #[test]
+    fn detects_chrome_without_chrome_path() {
+        let config = Config {
+            chrome_path: None,
+            ..config().clone()
+        };
+
+        match config.chrome_executable() {
+            Ok(path) => assert!(path.exists()),
+            Err(e) => assert!(e.contains("set CHROME_PATH"), "{e}"),
+        }
+    }
+}
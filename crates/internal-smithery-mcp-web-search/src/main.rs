@@ -1,4 +1,15 @@
+pub mod cache;
+pub mod config;
+pub mod metrics;
+pub mod order;
+pub mod retry;
+pub mod ssrf;
+#[cfg(test)]
+mod test_support;
 pub mod tool;
+pub mod worker;
+
+use std::{future::IntoFuture, sync::LazyLock};
 
 use axum::response::IntoResponse;
 use rmcp::{
@@ -9,6 +20,12 @@ use rmcp::{
         streamable_http_server::session::local::LocalSessionManager,
     },
 };
+use tokio::sync::Semaphore;
+
+/// Bounds the `search` calls running at once, separately from the tools
+/// fetching pages, so that a burst of one doesn't starve the other.
+static SEARCH_PERMITS: LazyLock<Semaphore> =
+    LazyLock::new(|| Semaphore::new(config::config().search_max_concurrency));
 
 #[derive(Debug, Clone)]
 pub struct Counter {
@@ -29,17 +46,310 @@ impl Counter {
     ))]
     async fn fetch(
         &self,
-        Parameters(tool::fetch::Input { urls }): Parameters<tool::fetch::Input>,
+        Parameters(input): Parameters<tool::fetch::Input>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let result_format = input.result_format.unwrap_or_default();
+
+        match tool::fetch::fetch(input).await {
+            Ok(fetch_results) => fetch_response(fetch_results, result_format),
+            Err(e) => {
+                let errors = vec![Content::text(e.to_string())];
+                Ok(rmcp::model::CallToolResult::error(errors))
+            }
+        }
+    }
+
+    /// Extracts the metadata of a web page as JSON: title, description, preview
+    /// image, canonical URL, author, published time, OpenGraph and Twitter card
+    /// fields, and every `<meta>` tag.
+    /// This is much lighter than fetching the full content, e.g. for link previews.
+    #[rmcp::tool(annotations(
+        title = "Extract page metadata.",
+        read_only_hint = true,
+        destructive_hint = false,
+        idempotent_hint = true,
+        open_world_hint = true
+    ))]
+    async fn metadata(
+        &self,
+        Parameters(input): Parameters<tool::metadata::Input>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        match tool::metadata::metadata(input).await {
+            Ok(metadata) => {
+                let content = serde_json::to_string(&metadata)
+                    .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+
+                Ok(rmcp::model::CallToolResult::success(vec![Content::text(
+                    content,
+                )]))
+            }
+            Err(e) => {
+                let errors = vec![Content::text(e.to_string())];
+                Ok(rmcp::model::CallToolResult::error(errors))
+            }
+        }
+    }
+
+    /// Fetches and parses a domain's `robots.txt`, returning the allowed and
+    /// disallowed paths, crawl delay and sitemaps applying to a user agent,
+    /// and whether a given `path` may be crawled.
+    /// Use this to check a site's crawl policy before scraping it.
+    #[rmcp::tool(annotations(
+        title = "Inspect robots.txt.",
+        read_only_hint = true,
+        destructive_hint = false,
+        idempotent_hint = true,
+        open_world_hint = true
+    ))]
+    async fn robots(
+        &self,
+        Parameters(input): Parameters<tool::robots::Input>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        match tool::robots::robots(input).await {
+            Ok(robots) => {
+                let content = serde_json::to_string(&robots)
+                    .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+
+                Ok(rmcp::model::CallToolResult::success(vec![Content::text(
+                    content,
+                )]))
+            }
+            Err(e) => {
+                let errors = vec![Content::text(e.to_string())];
+                Ok(rmcp::model::CallToolResult::error(errors))
+            }
+        }
+    }
+
+    /// Extracts the discussion thread of a page (forum, blog comments, etc.)
+    /// as a JSON tree of comments with their author, text, depth and replies.
+    /// Use this instead of `fetch` when the threaded structure matters.
+    #[rmcp::tool(annotations(
+        title = "Extract comment threads.",
+        read_only_hint = true,
+        destructive_hint = false,
+        idempotent_hint = true,
+        open_world_hint = true
+    ))]
+    async fn comments(
+        &self,
+        Parameters(input): Parameters<tool::comments::Input>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        match tool::comments::comments(input).await {
+            Ok(comments) => {
+                let content = serde_json::to_string(&comments)
+                    .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+
+                Ok(rmcp::model::CallToolResult::success(vec![Content::text(
+                    content,
+                )]))
+            }
+            Err(e) => {
+                let errors = vec![Content::text(e.to_string())];
+                Ok(rmcp::model::CallToolResult::error(errors))
+            }
+        }
+    }
+
+    /// Takes a structured snapshot of a web page in a single call: its markdown
+    /// content, title, links, images, metadata and detected language.
+    /// Use this to fully understand a page without multiple round trips.
+    #[rmcp::tool(annotations(
+        title = "Snapshot a web page.",
+        read_only_hint = true,
+        destructive_hint = false,
+        idempotent_hint = true,
+        open_world_hint = true
+    ))]
+    async fn snapshot(
+        &self,
+        Parameters(input): Parameters<tool::snapshot::Input>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        match tool::snapshot::snapshot(input).await {
+            Ok(snapshot) => {
+                let content = serde_json::to_string(&snapshot)
+                    .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+
+                Ok(rmcp::model::CallToolResult::success(vec![Content::text(
+                    content,
+                )]))
+            }
+            Err(e) => {
+                let errors = vec![Content::text(e.to_string())];
+                Ok(rmcp::model::CallToolResult::error(errors))
+            }
+        }
+    }
+
+    /// Extracts the forms of a web page as JSON: their action, method and
+    /// fields (name, type, label, required, options of selects).
+    /// Use this to understand what a form expects before filling it in.
+    #[rmcp::tool(annotations(
+        title = "Extract forms.",
+        read_only_hint = true,
+        destructive_hint = false,
+        idempotent_hint = true,
+        open_world_hint = true
+    ))]
+    async fn forms(
+        &self,
+        Parameters(input): Parameters<tool::forms::Input>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        match tool::forms::forms(input).await {
+            Ok(forms) => {
+                let content = serde_json::to_string(&forms)
+                    .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+
+                Ok(rmcp::model::CallToolResult::success(vec![Content::text(
+                    content,
+                )]))
+            }
+            Err(e) => {
+                let errors = vec![Content::text(e.to_string())];
+                Ok(rmcp::model::CallToolResult::error(errors))
+            }
+        }
+    }
+
+    /// Checks when web pages last changed, from their `Last-Modified` and
+    /// `ETag` headers, without downloading them.
+    /// Use this to decide which previously fetched pages need a re-fetch.
+    #[rmcp::tool(annotations(
+        title = "Check last-modified dates.",
+        read_only_hint = true,
+        destructive_hint = false,
+        idempotent_hint = true,
+        open_world_hint = true
+    ))]
+    async fn last_modified(
+        &self,
+        Parameters(input): Parameters<tool::last_modified::Input>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        match tool::last_modified::last_modified(input).await {
+            Ok(results) => {
+                let content = serde_json::to_string(&results)
+                    .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+
+                Ok(rmcp::model::CallToolResult::success(vec![Content::text(
+                    content,
+                )]))
+            }
+            Err(e) => {
+                let errors = vec![Content::text(e.to_string())];
+                Ok(rmcp::model::CallToolResult::error(errors))
+            }
+        }
+    }
+
+    /// Lists the hyperlinks of a web page as JSON (title, url, text), with
+    /// relative links resolved. Set `internal_only` or `external_only` to keep
+    /// only the links to the same host or to other hosts.
+    /// Use this to discover the pages to fetch next.
+    #[rmcp::tool(annotations(
+        title = "List links.",
+        read_only_hint = true,
+        destructive_hint = false,
+        idempotent_hint = true,
+        open_world_hint = true
+    ))]
+    async fn links(
+        &self,
+        Parameters(input): Parameters<tool::links::Input>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
-        let result = tool::fetch::fetch(urls).await;
+        match tool::links::links(input).await {
+            Ok(links) => {
+                let content = serde_json::to_string(&links)
+                    .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
 
-        match result {
-            Ok(markdown_list) => {
-                let results = markdown_list
-                    .into_iter()
-                    .map(|markdown| Content::text(markdown))
-                    .collect::<Vec<Content>>();
-                Ok(rmcp::model::CallToolResult::success(results))
+                Ok(rmcp::model::CallToolResult::success(vec![Content::text(
+                    content,
+                )]))
+            }
+            Err(e) => {
+                let errors = vec![Content::text(e.to_string())];
+                Ok(rmcp::model::CallToolResult::error(errors))
+            }
+        }
+    }
+
+    /// Captures a screenshot of a web page with the headless browser and
+    /// returns it as a PNG image. Set `full_page` to capture the whole page
+    /// rather than the viewport. Use this when the layout or visuals matter.
+    #[rmcp::tool(annotations(
+        title = "Capture a screenshot.",
+        read_only_hint = true,
+        destructive_hint = false,
+        idempotent_hint = true,
+        open_world_hint = true
+    ))]
+    async fn screenshot(
+        &self,
+        Parameters(input): Parameters<tool::screenshot::Input>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        match tool::screenshot::screenshot(input).await {
+            Ok(png) => Ok(rmcp::model::CallToolResult::success(vec![Content::image(
+                png,
+                "image/png",
+            )])),
+            Err(e) => {
+                let errors = vec![Content::text(e.to_string())];
+                Ok(rmcp::model::CallToolResult::error(errors))
+            }
+        }
+    }
+
+    /// Extracts the schema.org items (Product, Recipe, Article) a page embeds
+    /// as JSON-LD, microdata or RDFa, as structured JSON.
+    /// Use this on product or recipe pages instead of scraping their text.
+    #[rmcp::tool(annotations(
+        title = "Extract structured data.",
+        read_only_hint = true,
+        destructive_hint = false,
+        idempotent_hint = true,
+        open_world_hint = true
+    ))]
+    async fn structured_data(
+        &self,
+        Parameters(input): Parameters<tool::structured::Input>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        match tool::structured::structured_data(input).await {
+            Ok(items) => {
+                let content = serde_json::to_string(&items)
+                    .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+
+                Ok(rmcp::model::CallToolResult::success(vec![Content::text(
+                    content,
+                )]))
+            }
+            Err(e) => {
+                let errors = vec![Content::text(e.to_string())];
+                Ok(rmcp::model::CallToolResult::error(errors))
+            }
+        }
+    }
+
+    /// Fetches a page and returns its content with a hash of it. Pass the hash
+    /// of a previous call as `previous_hash` to learn whether the page `changed`,
+    /// without diffing the content yourself.
+    #[rmcp::tool(annotations(
+        title = "Monitor a page for changes.",
+        read_only_hint = true,
+        destructive_hint = false,
+        idempotent_hint = true,
+        open_world_hint = true
+    ))]
+    async fn monitor(
+        &self,
+        Parameters(input): Parameters<tool::monitor::Input>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        match tool::monitor::monitor(input).await {
+            Ok(monitor) => {
+                let content = serde_json::to_string(&monitor)
+                    .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+
+                Ok(rmcp::model::CallToolResult::success(vec![Content::text(
+                    content,
+                )]))
             }
             Err(e) => {
                 let errors = vec![Content::text(e.to_string())];
@@ -61,27 +371,23 @@ impl Counter {
     ))]
     async fn search(
         &self,
-        Parameters(tool::search::Input {
-            query,
-            include_domains,
-        }): Parameters<tool::search::Input>,
+        Parameters(input): Parameters<tool::search::Input>,
     ) -> Result<CallToolResult, rmcp::ErrorData> {
-        let response =
-            crate::tool::search::search(self.exa_api_key.clone(), query, include_domains).await;
+        let _permit = SEARCH_PERMITS
+            .acquire()
+            .await
+            .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
 
-        match response {
-            Ok(search_results) => {
-                let mut results = vec![];
+        input
+            .validate_published_dates()
+            .map_err(|e| rmcp::ErrorData::invalid_params(e, None))?;
 
-                for search_result in search_results {
-                    let content = serde_json::to_string(&search_result)
-                        .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+        let format = input.format.unwrap_or_default();
 
-                    results.push(Content::text(content));
-                }
+        let response = crate::tool::search::search(self.exa_api_key.clone(), input).await;
 
-                Ok(rmcp::model::CallToolResult::success(results))
-            }
+        match response {
+            Ok(response) => search_response(response, format),
             Err(e) => {
                 let errors = vec![Content::text(e.to_string())];
                 Ok(rmcp::model::CallToolResult::error(errors))
@@ -90,7 +396,84 @@ impl Counter {
     }
 }
 
-#[rmcp::tool_handler]
+/// Lays out the results of a `search` call, one content item per result.
+fn search_response(
+    response: tool::search::SearchResponse,
+    format: tool::search::Format,
+) -> Result<CallToolResult, rmcp::ErrorData> {
+    let mut results = vec![];
+
+    if let Some(expanded_query) = response.expanded_query {
+        results.push(Content::text(format!("Expanded query: {}", expanded_query)));
+    }
+
+    for search_result in response.results {
+        let content = match format {
+            tool::search::Format::Json => serde_json::to_string(&search_result)
+                .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?,
+            tool::search::Format::Citation => search_result.citation(),
+        };
+
+        results.push(Content::text(content));
+    }
+
+    Ok(rmcp::model::CallToolResult::success(results))
+}
+
+/// Lays out the results of a `fetch` call. The call only fails when nothing
+/// could be fetched at all; otherwise each result carries its own status.
+fn fetch_response(
+    fetch_results: Vec<tool::fetch::FetchResult>,
+    result_format: tool::fetch::ResultFormat,
+) -> Result<CallToolResult, rmcp::ErrorData> {
+    let all_failed = tool::fetch::all_failed(&fetch_results);
+
+    let mut results = vec![];
+
+    match result_format {
+        tool::fetch::ResultFormat::Separate => {
+            for fetch_result in fetch_results {
+                let content = serde_json::to_string(&fetch_result)
+                    .map_err(|e| rmcp::ErrorData::internal_error(e.to_string(), None))?;
+
+                results.push(Content::text(content));
+            }
+        }
+        tool::fetch::ResultFormat::Concatenated => {
+            results.push(Content::text(tool::fetch::concatenate(&fetch_results)));
+        }
+    }
+
+    if all_failed {
+        Ok(rmcp::model::CallToolResult::error(results))
+    } else {
+        Ok(rmcp::model::CallToolResult::success(results))
+    }
+}
+
+/// The tool router, recording every call in the metrics.
+struct MeteredRouter<'a>(&'a rmcp::handler::server::tool::ToolRouter<Counter>);
+
+impl MeteredRouter<'_> {
+    async fn call(
+        &self,
+        context: rmcp::handler::server::tool::ToolCallContext<'_, Counter>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        let tool = context.name().to_owned();
+
+        let result = self.0.call(context).await;
+
+        metrics::record_tool_call(&tool, &result);
+
+        result
+    }
+
+    fn list_all(&self) -> Vec<rmcp::model::Tool> {
+        self.0.list_all()
+    }
+}
+
+#[rmcp::tool_handler(router = MeteredRouter(&self.tool_router))]
 impl rmcp::ServerHandler for Counter {
     fn get_info(&self) -> rmcp::model::ServerInfo {
         rmcp::model::ServerInfo {
@@ -101,7 +484,7 @@ impl rmcp::ServerHandler for Counter {
             server_info: rmcp::model::Implementation {
                 name: "internal-smithery-mcp".to_owned(),
                 title: Some("Internal Smithery MCP".to_owned()),
-                version: "0.1.0".to_owned(),
+                version: env!("CARGO_PKG_VERSION").to_owned(),
                 icons: Some(vec![rmcp::model::Icon {
                     src: "https://www.ikuma.cloud/brand/favicon.svg".to_owned(),
                     mime_type: Some("image/svg+xml".to_owned()),
@@ -124,11 +507,17 @@ async fn handle_request(request: axum::http::Request<axum::body::Body>) -> impl
 
     let query_params = serde_qs::from_str::<QueryParams>(query_params_raw).unwrap_or_default();
 
+    let exa_api_key = if query_params.exa_api_key.is_empty() {
+        config::config().exa_api_key.clone().unwrap_or_default()
+    } else {
+        query_params.exa_api_key
+    };
+
     let service = StreamableHttpService::new(
         move || {
             Ok(Counter {
                 tool_router: Counter::tool_router(),
-                exa_api_key: query_params.exa_api_key.clone(),
+                exa_api_key: exa_api_key.clone(),
             })
         },
         std::sync::Arc::new(LocalSessionManager::default()),
@@ -138,16 +527,461 @@ async fn handle_request(request: axum::http::Request<axum::body::Body>) -> impl
         },
     );
 
-    let response = service.handle(request).await;
+    service.handle(request).await
+}
+
+/// Reports the effective configuration, with secrets redacted. Only routed
+/// when `EXPOSE_CONFIG` is set.
+async fn handle_config() -> impl IntoResponse {
+    axum::Json(config::config())
+}
 
-    response
+/// Liveness: the server is up.
+async fn handle_health() -> impl IntoResponse {
+    axum::Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
+
+/// How long the readiness probe waits for the browser.
+const READY_BROWSER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Readiness: the browser can be launched, unless disabled, and a default Exa
+/// API key is configured. Answers 503 with the failing checks otherwise.
+async fn handle_ready() -> impl IntoResponse {
+    let browser = if config::config().disable_browser {
+        Ok("disabled".to_owned())
+    } else {
+        match tokio::time::timeout(READY_BROWSER_TIMEOUT, tool::fetch::check_browser()).await {
+            Ok(Ok(())) => Ok("ok".to_owned()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(format!("no answer within {:?}", READY_BROWSER_TIMEOUT)),
+        }
+    };
+
+    let exa_api_key = match config::config().exa_api_key {
+        Some(_) => Ok("ok".to_owned()),
+        None => Err("EXA_API_KEY is not set".to_owned()),
+    };
+
+    let ready = browser.is_ok() && exa_api_key.is_ok();
+
+    let check = |result: Result<String, String>| match result {
+        Ok(status) => serde_json::json!({ "status": status }),
+        Err(e) => serde_json::json!({ "status": "error", "error": e }),
+    };
+
+    let status = if ready {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        axum::Json(serde_json::json!({
+            "status": if ready { "ready" } else { "not_ready" },
+            "checks": {
+                "browser": check(browser),
+                "exa_api_key": check(exa_api_key),
+            },
+        })),
+    )
+}
+
+async fn handle_metrics() -> impl IntoResponse {
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4",
+        )],
+        metrics::render(),
+    )
 }
 
 #[tokio::main]
 async fn main() {
-    let router: axum::Router =
-        axum::Router::new().route("/mcp", axum::routing::post(handle_request));
+    dotenvy::dotenv().ok();
+
+    init_tracing();
+
+    if std::env::args().skip(1).any(|arg| arg == "--check-config") {
+        std::process::exit(check_config());
+    }
+
+    // Resolve the configuration up front, so that its warnings show at startup
+    if config::config().exa_api_key.is_none() {
+        tracing::warn!(
+            "EXA_API_KEY is not set: search only works for clients passing `exa_api_key` in the MCP URL"
+        );
+    }
+
+    let router = router(config::config());
+
+    let bind_addr = config::bind_addr().unwrap_or_else(|e| {
+        tracing::error!("{}", e);
+        std::process::exit(1);
+    });
+
+    let listener = tokio::net::TcpListener::bind(bind_addr)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to listen on {}: {}", bind_addr, e);
+            std::process::exit(1);
+        });
+
+    tracing::info!("Listening on {}", bind_addr);
+
+    serve(
+        listener,
+        router,
+        shutdown_signal(),
+        config::config().shutdown_drain,
+    )
+    .await;
+
+    tool::fetch::shutdown_browser().await;
+}
+
+/// The HTTP routes of the server.
+fn router(config: &config::Config) -> axum::Router {
+    let router = axum::Router::new()
+        .route("/mcp", axum::routing::post(handle_request))
+        .route("/health", axum::routing::get(handle_health))
+        .route("/ready", axum::routing::get(handle_ready))
+        .route("/metrics", axum::routing::get(handle_metrics));
+
+    // Unauthenticated, so only served when the operator opts in
+    if config.expose_config {
+        router.route("/config", axum::routing::get(handle_config))
+    } else {
+        router
+    }
+}
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8081").await.unwrap();
-    axum::serve(listener, router).await.unwrap();
+/// Serves `router` until `signal` resolves, then stops accepting connections
+/// and waits up to `drain` for the in-flight requests.
+async fn serve(
+    listener: tokio::net::TcpListener,
+    router: axum::Router,
+    signal: impl Future<Output = ()> + Send + 'static,
+    drain: std::time::Duration,
+) {
+    let (shutdown_sender, mut shutdown_receiver) = tokio::sync::watch::channel(());
+
+    // Stops accepting connections on the signal, then waits for in-flight requests
+    let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+        signal.await;
+        tracing::info!("Shutting down, draining in-flight requests");
+        let _ = shutdown_sender.send(());
+    });
+
+    let drain_deadline = async {
+        if shutdown_receiver.changed().await.is_ok() {
+            tokio::time::sleep(drain).await;
+        } else {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    tokio::select! {
+        result = server.into_future() => result.unwrap(),
+        _ = drain_deadline => {
+            tracing::warn!("Drain timeout elapsed, exiting with requests still in flight");
+        }
+    }
+}
+
+/// Prints the resolved configuration and its problems without starting the
+/// server. Returns the exit code: non-zero when there is any problem.
+fn check_config() -> i32 {
+    match serde_json::to_string_pretty(config::config()) {
+        Ok(summary) => println!("{}", summary),
+        Err(e) => eprintln!("Failed to serialize the configuration: {}", e),
+    }
+
+    let problems = config::check();
+
+    if problems.is_empty() {
+        println!("Configuration OK");
+        return 0;
+    }
+
+    for problem in &problems {
+        eprintln!("error: {}", problem);
+    }
+    eprintln!("{} configuration problem(s) found", problems.len());
+
+    1
+}
+
+/// Installs the log subscriber, filtered by `RUST_LOG` (default: `info`).
+/// `LOG_FORMAT=json` logs one JSON object per line, for log collectors;
+/// anything else logs human-readable lines.
+fn init_tracing() {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+
+    let json =
+        std::env::var("LOG_FORMAT").is_ok_and(|format| format.trim().eq_ignore_ascii_case("json"));
+
+    if json {
+        builder.json().init();
+    } else {
+        builder.init();
+    }
+}
+
+/// Resolves on Ctrl+C or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tool::fetch::{FetchResult, ResultFormat};
+    use tool::search::{Format, SearchResponse, SearchResult};
+
+    fn ok(url: &str) -> FetchResult {
+        FetchResult::ok(url, "content".to_owned())
+    }
+
+    fn failed(url: &str) -> FetchResult {
+        FetchResult::error(url, "Error fetching".to_owned())
+    }
+
+    #[test]
+    fn fails_fetch_calls_only_when_every_url_failed() {
+        let is_error = |results: Vec<FetchResult>| {
+            fetch_response(results, ResultFormat::Separate)
+                .unwrap()
+                .is_error
+        };
+
+        assert_eq!(is_error(vec![failed("a"), failed("b")]), Some(true));
+        assert_eq!(is_error(vec![ok("a"), failed("b")]), Some(false));
+        assert_eq!(is_error(vec![ok("a"), ok("b")]), Some(false));
+    }
+
+    #[test]
+    fn lays_out_fetch_results_per_format() {
+        let texts = |result_format| {
+            fetch_response(vec![ok("a"), failed("b"), ok("c")], result_format)
+                .unwrap()
+                .content
+                .into_iter()
+                .map(|content| content.as_text().unwrap().text.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let separate = texts(ResultFormat::Separate);
+        assert_eq!(separate.len(), 3);
+        assert!(separate[1].contains("\"url\":\"b\""));
+
+        let concatenated = texts(ResultFormat::Concatenated);
+        assert_eq!(concatenated.len(), 1);
+        assert_eq!(concatenated[0].matches("\n\n---\n\n").count(), 2);
+        assert!(concatenated[0].starts_with("# a\n\ncontent"));
+    }
+
+    #[test]
+    fn writes_search_results_as_citations() {
+        let result = SearchResult {
+            title: "The [Rust] Book".to_owned(),
+            url: "https://doc.rust-lang.org/book/".to_owned(),
+            summary: "An introductory\n  book about Rust.".to_owned(),
+            text: None,
+            author: None,
+            published_date: None,
+            score: Some(0.5),
+            favicon: None,
+        };
+        let untitled = SearchResult {
+            title: " ".to_owned(),
+            url: "https://example.com/".to_owned(),
+            summary: String::new(),
+            ..result.clone()
+        };
+        let texts = |format| {
+            let response = SearchResponse {
+                results: vec![result.clone(), untitled.clone()],
+                expanded_query: None,
+            };
+
+            search_response(response, format)
+                .unwrap()
+                .content
+                .into_iter()
+                .map(|content| content.as_text().unwrap().text.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            texts(Format::Citation),
+            [
+                "[The \\[Rust\\] Book](https://doc.rust-lang.org/book/): An introductory book about Rust.",
+                "[https://example.com/](https://example.com/)",
+            ]
+        );
+
+        let json = texts(Format::Json);
+        assert_eq!(json.len(), 2);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json[0]).unwrap()["title"],
+            "The [Rust] Book"
+        );
+    }
+
+    async fn get(router: axum::Router, path: &str) -> (axum::http::StatusCode, String) {
+        use tower::ServiceExt;
+
+        let request = axum::http::Request::get(path)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn answers_health_checks() {
+        let (status, body) = get(router(config::config()), "/health").await;
+
+        assert_eq!(status, axum::http::StatusCode::OK);
+
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    }
+
+    #[tokio::test]
+    async fn routes_the_config_only_when_exposed() {
+        let hidden = config::Config {
+            expose_config: false,
+            ..config::config().clone()
+        };
+        let exposed = config::Config {
+            expose_config: true,
+            ..config::config().clone()
+        };
+
+        let (status, _) = get(router(&hidden), "/config").await;
+        assert_eq!(status, axum::http::StatusCode::NOT_FOUND);
+
+        let (status, _) = get(router(&exposed), "/config").await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+    }
+
+    /// Serves a route answering after `delay`, shutting down on `signal`.
+    /// Returns the address and a notification of the request arriving.
+    async fn serve_slow(
+        delay: std::time::Duration,
+        signal: tokio::sync::oneshot::Receiver<()>,
+        drain: std::time::Duration,
+    ) -> (
+        String,
+        std::sync::Arc<tokio::sync::Notify>,
+        tokio::task::JoinHandle<()>,
+    ) {
+        let arrived = std::sync::Arc::new(tokio::sync::Notify::new());
+        let notify = arrived.clone();
+
+        let router = axum::Router::new().route(
+            "/",
+            axum::routing::get(move || async move {
+                notify.notify_one();
+                tokio::time::sleep(delay).await;
+                "done"
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(serve(
+            listener,
+            router,
+            async move {
+                let _ = signal.await;
+            },
+            drain,
+        ));
+
+        (addr, arrived, server)
+    }
+
+    #[tokio::test]
+    async fn drains_in_flight_requests_on_shutdown() {
+        let (shutdown, signal) = tokio::sync::oneshot::channel();
+        let (addr, arrived, server) = serve_slow(
+            std::time::Duration::from_millis(300),
+            signal,
+            std::time::Duration::from_secs(10),
+        )
+        .await;
+
+        let request = tokio::spawn(reqwest::get(addr.clone()));
+        arrived.notified().await;
+        shutdown.send(()).unwrap();
+
+        let response = request.await.unwrap().unwrap();
+        assert_eq!(response.text().await.unwrap(), "done");
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+
+        // No longer accepting connections
+        assert!(reqwest::get(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn stops_waiting_at_the_drain_deadline() {
+        let (shutdown, signal) = tokio::sync::oneshot::channel();
+        let (addr, arrived, server) = serve_slow(
+            std::time::Duration::from_secs(60),
+            signal,
+            std::time::Duration::from_millis(100),
+        )
+        .await;
+
+        let _request = tokio::spawn(reqwest::get(addr));
+        arrived.notified().await;
+        shutdown.send(()).unwrap();
+
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .expect("the server should exit at the drain deadline")
+            .unwrap();
+    }
 }
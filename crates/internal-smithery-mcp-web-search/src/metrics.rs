@@ -0,0 +1,183 @@
+//! Operational metrics, served at `GET /metrics` in the Prometheus text format.
+//!
+//! Counters are labeled by tool and by source, e.g. `reqwest` or `browser`
+//! for fetch strategies, so that ratios are derived at query time: the
+//! average page size is `fetch_page_bytes_total / fetch_pages_total`, and the
+//! browser fallback ratio is the share of `fetch_attempts_total` made by the
+//! browser.
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// The latest value of each gauge, keyed by metric name.
+static GAUGES: Mutex<BTreeMap<String, f64>> = Mutex::new(BTreeMap::new());
+
+/// Label names and values, sorted by name.
+type Labels = Vec<(String, String)>;
+
+/// The value of each counter, keyed by metric name, then by labels.
+static COUNTERS: Mutex<BTreeMap<String, BTreeMap<Labels, f64>>> = Mutex::new(BTreeMap::new());
+
+pub fn set_gauge(name: &str, value: f64) {
+    if let Ok(mut gauges) = GAUGES.lock() {
+        gauges.insert(name.to_owned(), value);
+    }
+}
+
+/// Adds `value` to the counter `name` with the given labels.
+pub fn add(name: &str, labels: &[(&str, &str)], value: f64) {
+    let mut labels: Labels = labels
+        .iter()
+        .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+        .collect();
+    labels.sort();
+
+    if let Ok(mut counters) = COUNTERS.lock() {
+        *counters
+            .entry(name.to_owned())
+            .or_default()
+            .entry(labels)
+            .or_default() += value;
+    }
+}
+
+pub fn increment(name: &str, labels: &[(&str, &str)]) {
+    add(name, labels, 1.0);
+}
+
+/// Records a tool call by outcome, with the size of its response.
+/// `invalid` calls are those rejected before running, e.g. for bad arguments.
+pub fn record_tool_call(tool: &str, result: &Result<rmcp::model::CallToolResult, rmcp::ErrorData>) {
+    let outcome = match result {
+        Ok(result) if result.is_error == Some(true) => "error",
+        Ok(_) => "ok",
+        Err(_) => "invalid",
+    };
+
+    increment("tool_calls_total", &[("tool", tool), ("outcome", outcome)]);
+
+    if let Ok(result) = result {
+        let bytes: usize = result
+            .content
+            .iter()
+            .map(|content| match &content.raw {
+                rmcp::model::RawContent::Text(text) => text.text.len(),
+                rmcp::model::RawContent::Image(image) => image.data.len(),
+                _ => 0,
+            })
+            .sum();
+
+        add("tool_response_bytes_total", &[("tool", tool)], bytes as f64);
+    }
+}
+
+/// Records the rate-limit headers of an Exa response, e.g.
+/// `x-ratelimit-remaining`, as `exa_ratelimit_remaining`, and logs them so
+/// that operators can follow their budget.
+pub fn record_exa_rate_limit(headers: &reqwest::header::HeaderMap) {
+    for (name, value) in headers {
+        let name = name.as_str();
+
+        if !name.contains("ratelimit") {
+            continue;
+        }
+
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+
+        tracing::info!(header = name, value, "Exa rate limit");
+
+        if let Ok(value) = value.trim().parse::<f64>() {
+            let metric = format!("exa_{}", name.trim_start_matches("x-").replace('-', "_"));
+            set_gauge(&metric, value);
+        }
+    }
+}
+
+/// Escapes a label value: backslashes, quotes and line feeds.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+pub fn render() -> String {
+    let mut text = String::new();
+
+    if let Ok(gauges) = GAUGES.lock() {
+        for (name, value) in gauges.iter() {
+            text.push_str(&format!("# TYPE {name} gauge\n{name} {value}\n"));
+        }
+    }
+
+    if let Ok(counters) = COUNTERS.lock() {
+        for (name, series) in counters.iter() {
+            text.push_str(&format!("# TYPE {name} counter\n"));
+
+            for (labels, value) in series {
+                let labels = labels
+                    .iter()
+                    .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+                    .collect::<Vec<_>>()
+                    .join(",");
+
+                text.push_str(&format!("{name}{{{labels}}} {value}\n"));
+            }
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_exa_rate_limits_as_gauges() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", "42".parse().unwrap());
+        headers.insert("x-ratelimit-reset", "soon".parse().unwrap());
+        headers.insert("x-request-id", "7".parse().unwrap());
+
+        record_exa_rate_limit(&headers);
+
+        let text = render();
+        assert!(
+            text.contains("# TYPE exa_ratelimit_remaining gauge\nexa_ratelimit_remaining 42\n"),
+            "{text}"
+        );
+        // Only numeric rate-limit headers become gauges
+        assert!(!text.contains("exa_ratelimit_reset"));
+        assert!(!text.contains("exa_request_id"));
+    }
+
+    #[test]
+    fn renders_labeled_counters() {
+        add(
+            "test_labeled_total",
+            &[("tool", "fetch"), ("source", "a \"quoted\"\\path\nline")],
+            2.0,
+        );
+        // The same labels in another order are the same series
+        add(
+            "test_labeled_total",
+            &[("source", "a \"quoted\"\\path\nline"), ("tool", "fetch")],
+            1.0,
+        );
+        increment("test_labeled_total", &[("tool", "search")]);
+
+        let text = render();
+
+        assert!(
+            text.contains(concat!(
+                "# TYPE test_labeled_total counter\n",
+                "test_labeled_total{source=\"a \\\"quoted\\\"\\\\path\\nline\",tool=\"fetch\"} 3\n",
+                "test_labeled_total{tool=\"search\"} 1\n",
+            )),
+            "{text}"
+        );
+        assert_eq!(text.matches("# TYPE test_labeled_total ").count(), 1);
+    }
+}
@@ -0,0 +1,80 @@
+use rmcp::schemars::JsonSchema;
+use serde::Deserialize;
+
+/// The order in which a tool returns its results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    /// The order of the input: the requested URLs, or the search engine's ranking.
+    #[default]
+    Input,
+    /// Most relevant first. For `fetch` this is the same as `input`.
+    Relevance,
+    /// Alphabetically by title (or URL when there is no title).
+    Alphabetical,
+    /// Shortest content first, e.g. to fit as many results as possible in a budget.
+    ShortestFirst,
+}
+
+/// A result that can be reordered by [`Order`].
+pub trait Ordered {
+    /// The title, or URL, used for alphabetical ordering.
+    fn label(&self) -> &str;
+
+    /// The length of the content in characters.
+    fn content_length(&self) -> usize;
+}
+
+impl Order {
+    /// Sorts `items` in place. Sorting is stable, so ties keep the input order.
+    pub fn apply<T: Ordered>(self, items: &mut [T]) {
+        match self {
+            Order::Input | Order::Relevance => {}
+            Order::Alphabetical => items.sort_by_cached_key(|item| item.label().to_lowercase()),
+            Order::ShortestFirst => items.sort_by_cached_key(|item| item.content_length()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Item(&'static str, &'static str);
+
+    impl Ordered for Item {
+        fn label(&self) -> &str {
+            self.0
+        }
+
+        fn content_length(&self) -> usize {
+            self.1.chars().count()
+        }
+    }
+
+    fn labels(order: Order) -> Vec<&'static str> {
+        let mut items = [
+            Item("b", "medium"),
+            Item("C", "a longer content"),
+            Item("a", "tiny"),
+            Item("d", "same"),
+        ];
+
+        order.apply(&mut items);
+
+        items.iter().map(|item| item.0).collect()
+    }
+
+    #[test]
+    fn orders_by_content_length() {
+        // Ties keep the input order
+        assert_eq!(labels(Order::ShortestFirst), ["a", "d", "b", "C"]);
+    }
+
+    #[test]
+    fn orders_by_label_or_keeps_the_input() {
+        assert_eq!(labels(Order::Alphabetical), ["a", "b", "C", "d"]);
+        assert_eq!(labels(Order::Input), ["b", "C", "a", "d"]);
+        assert_eq!(labels(Order::Relevance), ["b", "C", "a", "d"]);
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// How failed requests are retried, shared by every tool so that tuning it
+/// in one place affects them consistently.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RetryPolicy {
+    /// `RETRY_MAX_ATTEMPTS`: attempts in total, including the first (default: 3).
+    pub max_attempts: u32,
+
+    /// `RETRY_BASE_DELAY_MS`: delay before the first retry, doubled for each
+    /// subsequent one (default: 200).
+    #[serde(rename = "base_delay_ms", serialize_with = "as_millis")]
+    pub base_delay: Duration,
+
+    /// `RETRY_MAX_DELAY_MS`: upper bound of the delay (default: 5000).
+    #[serde(rename = "max_delay_ms", serialize_with = "as_millis")]
+    pub max_delay: Duration,
+
+    /// `RETRY_JITTER`: randomize delays (default: `true`), so that clients
+    /// failing at the same time don't retry in lockstep.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            jitter: true,
+        }
+    }
+}
+
+/// Errors that may succeed when the operation is retried.
+pub trait Retryable {
+    fn is_retryable(&self) -> bool;
+}
+
+/// Transient network failures are worth retrying.
+impl Retryable for reqwest::Error {
+    fn is_retryable(&self) -> bool {
+        self.is_connect() || self.is_timeout()
+    }
+}
+
+impl RetryPolicy {
+    /// Computes the delay before retrying after `attempt` (0-based) failures.
+    ///
+    /// Uses exponential backoff, with full jitter when enabled: the delay is
+    /// then picked uniformly between zero and the capped exponential delay.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        if !self.jitter {
+            return exponential;
+        }
+
+        let millis = u64::try_from(exponential.as_millis()).unwrap_or(u64::MAX);
+
+        Duration::from_millis(rand::random_range(0..=millis))
+    }
+
+    /// Runs `operation` until it succeeds, fails with an error that isn't
+    /// retryable, or `max_attempts` is reached.
+    pub async fn retry<T, E, F, Fut>(&self, mut operation: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Retryable + std::fmt::Display,
+    {
+        let mut attempt = 0;
+
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt + 1 < self.max_attempts && e.is_retryable() => {
+                    let delay = self.backoff(attempt);
+                    tracing::warn!(
+                        "Attempt {} failed, retrying in {:?}: {}",
+                        attempt + 1,
+                        delay,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+fn as_millis<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    duration.as_millis().serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..Default::default()
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(200));
+        assert_eq!(policy.backoff(1), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(1600));
+        assert_eq!(policy.backoff(5), Duration::from_secs(5));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn jittered_backoff_varies_within_bounds() {
+        let policy = RetryPolicy::default();
+
+        let delays = (0..100).map(|_| policy.backoff(2)).collect::<Vec<_>>();
+
+        assert!(
+            delays
+                .iter()
+                .all(|delay| *delay <= Duration::from_millis(800))
+        );
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+    }
+
+    /// An error whose retryability is given.
+    #[derive(Debug)]
+    struct Failure(bool);
+
+    impl std::fmt::Display for Failure {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "failure")
+        }
+    }
+
+    impl Retryable for Failure {
+        fn is_retryable(&self) -> bool {
+            self.0
+        }
+    }
+
+    fn quick_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+            jitter: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_up_to_max_attempts() {
+        let mut attempts = 0;
+
+        let result: Result<(), _> = quick_policy(4)
+            .retry(|| {
+                attempts += 1;
+                async { Err(Failure(true)) }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 4);
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_first_success() {
+        let mut attempts = 0;
+
+        let result = quick_policy(4)
+            .retry(|| {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt < 2 {
+                        Err(Failure(true))
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+
+        assert_eq!(result.ok(), Some(2));
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_permanent_errors() {
+        let mut attempts = 0;
+
+        let result: Result<(), _> = quick_policy(4)
+            .retry(|| {
+                attempts += 1;
+                async { Err(Failure(false)) }
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,497 @@
+//! Guards against server-side request forgery: URLs given by a client must
+//! not make the server reach its own network, e.g. cloud metadata endpoints
+//! or the MCP server itself.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+/// Upper bound of redirects followed per request, as reqwest's default policy.
+const MAX_REDIRECTS: usize = 10;
+
+/// A request refused because its target is not publicly routable.
+#[derive(Debug)]
+pub struct Blocked {
+    pub url: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for Blocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Blocked request to {}: {}", self.url, self.reason)
+    }
+}
+
+impl std::error::Error for Blocked {}
+
+impl Blocked {
+    /// Finds a `Blocked` error in the chain of sources of `error`, e.g. one
+    /// raised by the redirect policy and wrapped by reqwest.
+    pub fn find<'a>(error: &'a (dyn std::error::Error + 'static)) -> Option<&'a Blocked> {
+        let mut source = Some(error);
+
+        while let Some(error) = source {
+            if let Some(blocked) = error.downcast_ref::<Blocked>() {
+                return Some(blocked);
+            }
+            source = error.source();
+        }
+
+        None
+    }
+}
+
+/// A host that didn't resolve within `FETCH_DNS_TIMEOUT_SECS`.
+#[derive(Debug)]
+pub struct DnsTimeout {
+    pub host: String,
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for DnsTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Resolving {} timed out after {:?}",
+            self.host, self.timeout
+        )
+    }
+}
+
+impl std::error::Error for DnsTimeout {}
+
+/// Resolves a host, failing with [`DnsTimeout`] when it takes too long.
+async fn lookup_host(
+    host: &str,
+    port: u16,
+) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    let timeout = crate::config::config().fetch_dns_timeout;
+
+    resolve_within(host, timeout, tokio::net::lookup_host((host, port))).await
+}
+
+/// Awaits the resolution of `host`, failing with [`DnsTimeout`] after `timeout`.
+async fn resolve_within<I: Iterator<Item = SocketAddr>>(
+    host: &str,
+    timeout: Duration,
+    resolution: impl Future<Output = std::io::Result<I>>,
+) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    match tokio::time::timeout(timeout, resolution).await {
+        Ok(addresses) => Ok(addresses?.collect()),
+        Err(_) => {
+            let timed_out = DnsTimeout {
+                host: host.to_owned(),
+                timeout,
+            };
+            tracing::warn!("{}", timed_out);
+            Err(timed_out.into())
+        }
+    }
+}
+
+/// Whether an address is loopback, private, link-local or otherwise not
+/// publicly routable.
+pub fn is_blocked_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                // "This network", 0.0.0.0/8, which reaches the host itself
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_blocked_ip(IpAddr::V4(ip));
+            }
+
+            let first = ip.segments()[0];
+
+            ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local, fc00::/7
+                || (first & 0xfe00) == 0xfc00
+                // Link-local, fe80::/10
+                || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// Checks that a URL targets a public `http` or `https` host, as far as can
+/// be told without resolving it.
+pub fn check_url(url: &reqwest::Url) -> Result<(), Blocked> {
+    let blocked = |reason: &str| Blocked {
+        url: url.to_string(),
+        reason: reason.to_owned(),
+    };
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(blocked(&format!(
+            "the {} scheme is not allowed, only http and https are",
+            url.scheme()
+        )));
+    }
+
+    if crate::config::config().fetch_allow_private_ips || is_test_server(url) {
+        return Ok(());
+    }
+
+    match url.host() {
+        Some(url::Host::Ipv4(ip)) if is_blocked_ip(IpAddr::V4(ip)) => {
+            Err(blocked("the address is private or loopback"))
+        }
+        Some(url::Host::Ipv6(ip)) if is_blocked_ip(IpAddr::V6(ip)) => {
+            Err(blocked("the address is private or loopback"))
+        }
+        Some(url::Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+
+            if domain == "localhost" || domain.ends_with(".localhost") {
+                Err(blocked("the host is local"))
+            } else {
+                Ok(())
+            }
+        }
+        Some(_) => Ok(()),
+        None => Err(blocked("the URL has no host")),
+    }
+}
+
+/// Mock servers the tests may reach, although they listen on loopback. Shared
+/// by every thread, as the browser checks its requests from threads of its own.
+#[cfg(test)]
+static TEST_SERVERS: std::sync::Mutex<Vec<SocketAddr>> = std::sync::Mutex::new(Vec::new());
+
+/// Lets the tests reach a mock server. Any other loopback address or port
+/// stays blocked.
+#[cfg(test)]
+pub fn allow_test_server(address: SocketAddr) {
+    TEST_SERVERS.lock().unwrap().push(address);
+}
+
+#[cfg(test)]
+fn is_test_server(url: &reqwest::Url) -> bool {
+    let ip = match url.host() {
+        Some(url::Host::Ipv4(ip)) => IpAddr::V4(ip),
+        Some(url::Host::Ipv6(ip)) => IpAddr::V6(ip),
+        _ => return false,
+    };
+
+    let Some(port) = url.port_or_known_default() else {
+        return false;
+    };
+
+    TEST_SERVERS
+        .lock()
+        .unwrap()
+        .contains(&SocketAddr::new(ip, port))
+}
+
+#[cfg(not(test))]
+fn is_test_server(_url: &reqwest::Url) -> bool {
+    false
+}
+
+/// Checks a URL before anything is requested from it, resolving its host to
+/// catch public names pointing at internal addresses. Fails with [`Blocked`],
+/// or [`DnsTimeout`] when the host takes too long to resolve.
+pub async fn check_target(
+    url: &reqwest::Url,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    check_url(url)?;
+
+    if crate::config::config().fetch_allow_private_ips || is_test_server(url) {
+        return Ok(());
+    }
+
+    let (Some(url::Host::Domain(domain)), Some(port)) = (url.host(), url.port_or_known_default())
+    else {
+        return Ok(());
+    };
+
+    // A name that doesn't resolve is reported by the client when it tries
+    let addresses = match lookup_host(domain, port).await {
+        Ok(addresses) => addresses,
+        Err(e) if e.is::<DnsTimeout>() => return Err(e),
+        Err(_) => return Ok(()),
+    };
+
+    check_addresses(url, domain, &addresses)?;
+
+    Ok(())
+}
+
+/// Checks a URL like [`check_target`], from a thread that can't await, such
+/// as a browser worker or the browser's event handler. The host is resolved
+/// on a helper thread, so that a hanging resolver can't outlast
+/// `FETCH_DNS_TIMEOUT_SECS`.
+pub fn check_target_blocking(
+    url: &reqwest::Url,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    check_url(url)?;
+
+    if crate::config::config().fetch_allow_private_ips || is_test_server(url) {
+        return Ok(());
+    }
+
+    let (Some(url::Host::Domain(domain)), Some(port)) = (url.host(), url.port_or_known_default())
+    else {
+        return Ok(());
+    };
+
+    let timeout = crate::config::config().fetch_dns_timeout;
+    let (sender, receiver) = std::sync::mpsc::channel();
+
+    let host = domain.to_owned();
+    std::thread::spawn(move || {
+        use std::net::ToSocketAddrs;
+
+        let addresses = (host.as_str(), port)
+            .to_socket_addrs()
+            .map(|addresses| addresses.collect::<Vec<_>>());
+        let _ = sender.send(addresses);
+    });
+
+    match receiver.recv_timeout(timeout) {
+        Ok(Ok(addresses)) => Ok(check_addresses(url, domain, &addresses)?),
+        // A name that doesn't resolve is reported by the browser when it tries
+        Ok(Err(_)) => Ok(()),
+        Err(_) => {
+            let timed_out = DnsTimeout {
+                host: domain.to_owned(),
+                timeout,
+            };
+            tracing::warn!("{}", timed_out);
+            Err(timed_out.into())
+        }
+    }
+}
+
+/// Fails when any address `domain` resolved to is blocked.
+fn check_addresses(
+    url: &reqwest::Url,
+    domain: &str,
+    addresses: &[SocketAddr],
+) -> Result<(), Blocked> {
+    match addresses.iter().find(|address| is_blocked_ip(address.ip())) {
+        Some(address) => Err(Blocked {
+            url: url.to_string(),
+            reason: format!(
+                "{} resolves to the private address {}",
+                domain,
+                address.ip()
+            ),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Resolves names for reqwest, refusing those pointing at blocked addresses.
+/// Checking at connection time, not only before the request, also covers a
+/// name rebound to an internal address in between.
+pub struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            let addresses = lookup_host(host, 0).await?;
+
+            if !crate::config::config().fetch_allow_private_ips
+                && let Some(address) = addresses.iter().find(|address| is_blocked_ip(address.ip()))
+            {
+                let blocked = Blocked {
+                    url: host.to_owned(),
+                    reason: format!("{} resolves to the private address {}", host, address.ip()),
+                };
+                tracing::warn!("{}", blocked);
+                return Err(blocked.into());
+            }
+
+            Ok(Box::new(addresses.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// A redirect policy validating every hop, since a public URL can redirect
+/// to an internal one.
+pub fn redirect_policy() -> reqwest::redirect::Policy {
+    reqwest::redirect::Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            return attempt.error("too many redirects");
+        }
+
+        match check_url(attempt.url()) {
+            Ok(()) => attempt.follow(),
+            Err(blocked) => {
+                tracing::warn!("{}", blocked);
+                attempt.error(blocked)
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    fn url(url: &str) -> reqwest::Url {
+        reqwest::Url::parse(url).unwrap()
+    }
+
+    fn blocked(ip: &str) -> bool {
+        is_blocked_ip(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn blocks_internal_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "127.255.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "100.127.255.255",
+            "0.0.0.0",
+            "0.1.2.3",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(blocked(ip), "{ip} should be blocked");
+        }
+
+        assert!(is_blocked_ip(IpAddr::V4(Ipv4Addr::UNSPECIFIED)));
+        assert!(is_blocked_ip(IpAddr::V6(Ipv6Addr::UNSPECIFIED)));
+    }
+
+    #[test]
+    fn allows_public_addresses() {
+        for ip in [
+            "93.184.216.34",
+            "8.8.8.8",
+            "100.63.255.255",
+            "100.128.0.1",
+            "172.32.0.1",
+            "2606:4700::1111",
+            "::ffff:8.8.8.8",
+        ] {
+            assert!(!blocked(ip), "{ip} should be allowed");
+        }
+    }
+
+    #[test]
+    fn checks_urls_without_resolving() {
+        assert!(check_url(&url("https://example.com/")).is_ok());
+        assert!(check_url(&url("http://93.184.216.34:8080/")).is_ok());
+
+        for target in [
+            "http://127.0.0.1:8081/mcp",
+            "http://0.0.0.0:8081/",
+            "http://[::]/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://localhost/",
+            "http://LOCALHOST./",
+            "http://admin.localhost/",
+            "http://[::ffff:7f00:1]/",
+            "file:///etc/passwd",
+            "gopher://example.com/",
+        ] {
+            assert!(
+                check_url(&url(target)).is_err(),
+                "{target} should be blocked"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_targets_before_requests() {
+        let blocked = check_target(&url("http://127.0.0.1:8081/"))
+            .await
+            .unwrap_err();
+        assert!(blocked.is::<Blocked>());
+
+        assert!(check_target(&url("https://93.184.216.34/")).await.is_ok());
+    }
+
+    /// An error wrapping another, as reqwest wraps the redirect policy's.
+    #[derive(Debug)]
+    struct Wrapper(Box<dyn std::error::Error + Send + Sync>);
+
+    impl std::fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "request failed")
+        }
+    }
+
+    impl std::error::Error for Wrapper {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(self.0.as_ref())
+        }
+    }
+
+    #[test]
+    fn finds_blocked_errors_in_sources() {
+        let blocked = Blocked {
+            url: "http://127.0.0.1/".to_owned(),
+            reason: "the address is private or loopback".to_owned(),
+        };
+        let wrapped = Wrapper(Box::new(Wrapper(blocked.into())));
+
+        assert_eq!(
+            Blocked::find(&wrapped).map(|blocked| blocked.url.as_str()),
+            Some("http://127.0.0.1/")
+        );
+        assert!(Blocked::find(&Wrapper("other".into())).is_none());
+    }
+
+    #[test]
+    fn checks_targets_from_blocking_threads() {
+        let blocked = check_target_blocking(&url("http://127.0.0.1:8081/")).unwrap_err();
+        assert!(blocked.is::<Blocked>());
+
+        assert!(check_target_blocking(&url("http://[::1]/")).is_err());
+        assert!(check_target_blocking(&url("ftp://example.com/")).is_err());
+        assert!(check_target_blocking(&url("https://93.184.216.34/")).is_ok());
+    }
+
+    #[tokio::test]
+    async fn times_out_stalled_resolutions() {
+        let stalled = std::future::pending::<std::io::Result<std::vec::IntoIter<SocketAddr>>>();
+
+        let error = resolve_within("stalled.example", Duration::from_millis(50), stalled)
+            .await
+            .unwrap_err();
+
+        let timed_out = error.downcast_ref::<DnsTimeout>().unwrap();
+        assert_eq!(timed_out.host, "stalled.example");
+        assert_eq!(timed_out.timeout, Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn returns_resolutions_within_the_timeout() {
+        let address: SocketAddr = "93.184.216.34:443".parse().unwrap();
+        let resolved = std::future::ready(Ok(vec![address].into_iter()));
+
+        let addresses = resolve_within("example.com", Duration::from_secs(1), resolved)
+            .await
+            .unwrap();
+
+        assert_eq!(addresses, [address]);
+    }
+}
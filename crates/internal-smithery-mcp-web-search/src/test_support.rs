@@ -0,0 +1,121 @@
+//! Helpers shared by the unit tests.
+
+use std::net::SocketAddr;
+
+/// Serves `router` on an ephemeral loopback port, which the SSRF guard lets
+/// the tests reach. Returns the base URL, e.g. `http://127.0.0.1:1234`.
+pub async fn serve(router: axum::Router) -> String {
+    let address = listen(router).await;
+
+    crate::ssrf::allow_test_server(address);
+
+    format!("http://{}", address)
+}
+
+/// Serves `router` on an ephemeral loopback port that the SSRF guard keeps
+/// blocking, standing for a service of the server's own network.
+pub async fn serve_internal(router: axum::Router) -> String {
+    format!("http://{}", listen(router).await)
+}
+
+async fn listen(router: axum::Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    address
+}
+
+/// Whether the tests driving Chrome can run. They return early when no
+/// binary is found, e.g. on machines without Chrome.
+pub fn browser_available() -> bool {
+    let config = crate::config::config();
+
+    !config.disable_browser && config.chrome_executable().is_ok()
+}
+
+/// The fields recorded on spans, as `(span, field, value)`, for asserting
+/// what the tracing output would show.
+#[derive(Debug, Clone, Default)]
+pub struct CapturedSpans(std::sync::Arc<std::sync::Mutex<Vec<(String, String, String)>>>);
+
+impl CapturedSpans {
+    /// The values recorded for `field` on the spans named `span`, in order.
+    pub fn values(&self, span: &str, field: &str) -> Vec<String> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, recorded, _)| name == span && recorded == field)
+            .map(|(_, _, value)| value.clone())
+            .collect()
+    }
+}
+
+/// Captures the spans of the current thread until the guard is dropped.
+/// Meant for `#[tokio::test]`, whose runtime runs on the test thread.
+pub fn capture_spans() -> (CapturedSpans, tracing::subscriber::DefaultGuard) {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let captured = CapturedSpans::default();
+    let subscriber = tracing_subscriber::registry().with(captured.clone());
+
+    (captured, tracing::subscriber::set_default(subscriber))
+}
+
+struct FieldVisitor<'a> {
+    span: &'static str,
+    records: &'a mut Vec<(String, String, String)>,
+}
+
+impl tracing::field::Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.records.push((
+            self.span.to_owned(),
+            field.name().to_owned(),
+            value.to_owned(),
+        ));
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.records.push((
+            self.span.to_owned(),
+            field.name().to_owned(),
+            format!("{:?}", value),
+        ));
+    }
+}
+
+impl<S> tracing_subscriber::Layer<S> for CapturedSpans
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    fn on_new_span(
+        &self,
+        attributes: &tracing::span::Attributes<'_>,
+        _: &tracing::span::Id,
+        _: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        attributes.record(&mut FieldVisitor {
+            span: attributes.metadata().name(),
+            records: &mut self.0.lock().unwrap(),
+        });
+    }
+
+    fn on_record(
+        &self,
+        id: &tracing::span::Id,
+        values: &tracing::span::Record<'_>,
+        context: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let Some(span) = context.span(id) else {
+            return;
+        };
+
+        values.record(&mut FieldVisitor {
+            span: span.name(),
+            records: &mut self.0.lock().unwrap(),
+        });
+    }
+}
@@ -0,0 +1,209 @@
+use rmcp::schemars::JsonSchema;
+use scraper::{ElementRef, Html, Node};
+use serde::{Deserialize, Serialize};
+
+use crate::tool::fetch::Error;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The URL of the discussion page, e.g. a forum thread.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Comment {
+    pub author: Option<String>,
+    pub text: String,
+    /// 0 for top-level comments, increasing with each level of replies.
+    pub depth: usize,
+    pub replies: Vec<Comment>,
+}
+
+/// Whether the element's class or id hints at a discussion.
+fn is_discussion(element: ElementRef) -> bool {
+    let value = element.value();
+
+    value
+        .attr("class")
+        .into_iter()
+        .chain(value.attr("id"))
+        .any(|attr| {
+            let attr = attr.to_ascii_lowercase();
+            ["comment", "repl", "discussion", "thread"]
+                .iter()
+                .any(|hint| attr.contains(hint))
+        })
+}
+
+fn is_author(element: ElementRef) -> bool {
+    let value = element.value();
+
+    value.name() == "cite"
+        || value.attr("rel") == Some("author")
+        || value.attr("class").is_some_and(|class| {
+            let class = class.to_ascii_lowercase();
+            ["author", "user"].iter().any(|hint| class.contains(hint))
+        })
+}
+
+fn is_list(element: ElementRef) -> bool {
+    matches!(element.value().name(), "ul" | "ol")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Collects the list items under `node` as comments at `depth`.
+fn collect(node: ElementRef, depth: usize, comments: &mut Vec<Comment>) {
+    for child in node.children().filter_map(ElementRef::wrap) {
+        if child.value().name() == "li" {
+            let mut comment = Comment {
+                depth,
+                ..Default::default()
+            };
+            let mut text = String::new();
+
+            gather(child, &mut comment, &mut text);
+
+            comment.text = collapse_whitespace(&text);
+
+            if !comment.text.is_empty() || !comment.replies.is_empty() {
+                comments.push(comment);
+            }
+        } else {
+            collect(child, depth, comments);
+        }
+    }
+}
+
+/// Reads a comment's own author and text; nested lists become its replies.
+fn gather(node: ElementRef, comment: &mut Comment, text: &mut String) {
+    for child in node.children() {
+        match child.value() {
+            Node::Text(t) => {
+                text.push_str(t);
+                text.push(' ');
+            }
+            Node::Element(_) => {
+                let Some(element) = ElementRef::wrap(child) else {
+                    continue;
+                };
+
+                if is_list(element) {
+                    collect(element, comment.depth + 1, &mut comment.replies);
+                } else if comment.author.is_none() && is_author(element) {
+                    let author = collapse_whitespace(&element.text().collect::<String>());
+                    comment.author = (!author.is_empty()).then_some(author);
+                } else if !matches!(element.value().name(), "script" | "style") {
+                    gather(element, comment, text);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Extracts the comment tree from nested lists found in discussion containers.
+fn extract_comments(html: &str) -> Vec<Comment> {
+    let document = Html::parse_document(html);
+    let list_selector = scraper::Selector::parse("ul, ol").unwrap();
+
+    let mut comments = vec![];
+
+    for list in document.select(&list_selector) {
+        let in_discussion = is_discussion(list)
+            || list
+                .parent()
+                .and_then(ElementRef::wrap)
+                .is_some_and(is_discussion);
+
+        // Lists nested in a list item are replies, collected from their root
+        let nested = list
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|ancestor| ancestor.value().name() == "li");
+
+        if in_discussion && !nested {
+            collect(list, 0, &mut comments);
+        }
+    }
+
+    comments
+}
+
+pub async fn comments(Input { url }: Input) -> Result<Vec<Comment>, Error> {
+    let client = crate::tool::fetch::http_client()?;
+
+    tracing::info!("Fetching comments: {}", url);
+
+    let (_, html) = crate::tool::fetch::fetch_html(&client, &url).await?;
+
+    Ok(extract_comments(&html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn refuses_internal_targets() {
+        let error = comments(Input {
+            url: "http://127.0.0.1:8081/mcp".to_owned(),
+        })
+        .await
+        .unwrap_err();
+
+        assert!(error.is::<crate::ssrf::Blocked>());
+    }
+
+    #[test]
+    fn extracts_threaded_comments() {
+        let html = r#"<html><body>
+            <nav><ul><li>Home</li><li>About</li></ul></nav>
+            <section id="comments">
+                <ul class="comment-list">
+                    <li><span class="author">alice</span><p>First!</p>
+                        <ul>
+                            <li><cite>bob</cite><p>Welcome, alice.</p>
+                                <ol>
+                                    <li><a rel="author" href="/u/alice">alice</a> <b>Thanks</b> bob.</li>
+                                </ol>
+                            </li>
+                            <li><span class="username">carol</span>Me too.</li>
+                        </ul>
+                    </li>
+                    <li><span class="author">dave</span><p>A second thread.</p><script>track()</script></li>
+                </ul>
+            </section>
+        </body></html>"#;
+
+        let comments = extract_comments(html);
+
+        // The navigation list is not a discussion
+        assert_eq!(comments.len(), 2);
+
+        let first = &comments[0];
+        assert_eq!(first.author.as_deref(), Some("alice"));
+        assert_eq!(first.text, "First!");
+        assert_eq!(first.depth, 0);
+        assert_eq!(first.replies.len(), 2);
+
+        let reply = &first.replies[0];
+        assert_eq!(reply.author.as_deref(), Some("bob"));
+        assert_eq!(reply.text, "Welcome, alice.");
+        assert_eq!(reply.depth, 1);
+
+        let nested = &reply.replies[0];
+        assert_eq!(nested.author.as_deref(), Some("alice"));
+        assert_eq!(nested.text, "Thanks bob.");
+        assert_eq!(nested.depth, 2);
+
+        assert_eq!(first.replies[1].author.as_deref(), Some("carol"));
+        assert_eq!(first.replies[1].text, "Me too.");
+
+        assert_eq!(comments[1].author.as_deref(), Some("dave"));
+        assert_eq!(comments[1].text, "A second thread.");
+        assert!(comments[1].replies.is_empty());
+    }
+}
@@ -0,0 +1,825 @@
+use std::{sync::Arc, time::Duration};
+
+use headless_chrome::{
+    Tab,
+    browser::tab::RequestPausedDecision,
+    protocol::cdp::{
+        Fetch::{FailRequest, RequestPattern, RequestStage, events::RequestPausedEvent},
+        Network::ErrorReason,
+    },
+};
+
+use super::{Error, FetchOptions};
+
+struct FlexibleWaiter<'a> {
+    tab: &'a Tab,
+    timeout: Duration,
+}
+
+impl<'a> FlexibleWaiter<'a> {
+    fn new(tab: &'a Tab) -> Self {
+        Self {
+            tab,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn wait_smart(&self) -> Result<(), Error> {
+        let start = std::time::Instant::now();
+
+        let common_selectors = vec![
+            "main",
+            "article",
+            "[role='main']",
+            ".content",
+            ".main-content",
+            "#content",
+            "[data-testid]",
+            "[data-component]",
+        ];
+
+        while start.elapsed() < self.timeout {
+            for selector in &common_selectors {
+                if self.tab.find_element(selector).is_ok() {
+                    tracing::info!("Found element with selector: {}", selector);
+                    return Ok(());
+                }
+            }
+
+            let has_content = self
+                .tab
+                .evaluate(
+                    r#"
+                // Check whether the body has sufficient content
+                document.body.innerText.length > 100 &&
+                // Check for a minimal DOM structure
+                document.body.children.length > 0
+                "#,
+                    false,
+                )?
+                .value
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            if has_content {
+                tracing::info!("Found content by checking body");
+                return Ok(());
+            }
+
+            std::thread::sleep(Duration::from_millis(200));
+        }
+
+        Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "Timeout: No suitable element found",
+        )))
+    }
+}
+
+/// Clicks the accept button of common cookie-consent overlays, found by
+/// the selectors of popular consent managers or by the button's text. Text
+/// matches only count inside a consent container, so that a generic "OK"
+/// doesn't confirm some other dialog of the page.
+const ACCEPT_COOKIES_SCRIPT: &str = r##"
+(() => {
+    const selectors = [
+        "#onetrust-accept-btn-handler",
+        "#CybotCookiebotDialogBodyLevelButtonLevelOptinAllowAll",
+        "#CybotCookiebotDialogBodyButtonAccept",
+        "#didomi-notice-agree-button",
+        "[data-testid='uc-accept-all-button']",
+        ".fc-cta-consent",
+        ".cc-allow",
+        ".cc-accept",
+    ];
+    for (const selector of selectors) {
+        const element = document.querySelector(selector);
+        if (element) {
+            element.click();
+            return true;
+        }
+    }
+    const labels = [
+        "accept all", "accept all cookies", "accept", "accept cookies", "i accept",
+        "agree", "i agree", "allow all", "allow cookies", "got it", "ok",
+        "alle akzeptieren", "akzeptieren", "tout accepter", "accepter",
+        "aceptar todo", "aceptar", "accetta tutto", "accetta", "alles accepteren",
+    ];
+    const inConsentContainer = (element) => {
+        for (let node = element.parentElement; node; node = node.parentElement) {
+            const marker = `${node.id} ${node.getAttribute("class") || ""}`;
+            if (/cookie|consent|gdpr|cmp/i.test(marker)) {
+                return true;
+            }
+        }
+        return false;
+    };
+    const candidates = Array.from(document.querySelectorAll(
+        "button, [role='button'], a, input[type='button'], input[type='submit']"
+    )).filter(inConsentContainer);
+    for (const label of labels) {
+        const element = candidates.find(
+            (c) => (c.innerText || c.value || "").trim().toLowerCase() === label
+        );
+        if (element) {
+            element.click();
+            return true;
+        }
+    }
+    return false;
+})()
+"##;
+
+/// Finds the frame named `__FRAME_NAME__` (by `name` or `id`), returning its
+/// document when the page may read it, or else its URL.
+const FIND_FRAME_SCRIPT: &str = r##"
+(() => {
+    const name = __FRAME_NAME__;
+    const frame = Array.from(document.querySelectorAll('iframe, frame'))
+        .find((frame) => frame.name === name || frame.id === name);
+
+    if (!frame) {
+        return null;
+    }
+
+    try {
+        const root = frame.contentDocument && frame.contentDocument.documentElement;
+        if (root) {
+            return JSON.stringify({ html: root.outerHTML });
+        }
+    } catch (e) {
+        // Cross-origin frames can't be read from the page
+    }
+
+    return JSON.stringify({ src: frame.src });
+})()
+"##;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum FrameContent {
+    Html(String),
+    Src(String),
+}
+
+/// No frame of the page has the requested name.
+#[derive(Debug)]
+pub(super) struct FrameNotFound(String);
+
+impl std::fmt::Display for FrameNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no frame named {:?} was found on the page", self.0)
+    }
+}
+
+impl std::error::Error for FrameNotFound {}
+
+/// Extracts the HTML of a frame, navigating to it when it is cross-origin.
+fn frame_html(tab: &Tab, name: &str, options: &FetchOptions) -> Result<String, Error> {
+    let script = FIND_FRAME_SCRIPT.replace("__FRAME_NAME__", &serde_json::to_string(name)?);
+
+    let found = tab
+        .evaluate(&script, false)?
+        .value
+        .and_then(|value| value.as_str().map(str::to_owned))
+        .ok_or_else(|| FrameNotFound(name.to_owned()))?;
+
+    match serde_json::from_str(&found)? {
+        FrameContent::Html(html) => Ok(html),
+        FrameContent::Src(src) => {
+            let src = reqwest::Url::parse(&src)?;
+            crate::ssrf::check_target_blocking(&src)?;
+
+            tracing::info!("Navigating to cross-origin frame: {}", src);
+
+            tab.navigate_to(src.as_str())?;
+            check_navigated_url(tab)?;
+
+            FlexibleWaiter::new(tab)
+                .with_timeout(options.timeout.unwrap_or(Duration::from_secs(15)))
+                .wait_smart()?;
+
+            Ok(tab.wait_for_element("body")?.get_content()?)
+        }
+    }
+}
+
+pub(super) fn fetch_with_browser(
+    browser: &headless_chrome::Browser,
+    url: &str,
+    options: &FetchOptions,
+) -> Result<String, Error> {
+    tracing::info!("Fetching with browser: {}", url);
+
+    retry_in_new_tab(url, || fetch_in_new_tab(browser, url, options))
+}
+
+/// Runs `attempt`, which opens its own tab, once more when it fails. A tab
+/// stuck on a dialog or with a crashed renderer is usually a transient
+/// issue, unlike a closed browser or a page missing what was asked for.
+fn retry_in_new_tab<T>(
+    url: &str,
+    mut attempt: impl FnMut() -> Result<T, Error>,
+) -> Result<T, Error> {
+    match attempt() {
+        Err(e)
+            if !e.is::<BrowserClosed>()
+                && !e.is::<FrameNotFound>()
+                && !e.is::<super::convert::SelectorNotFound>() =>
+        {
+            tracing::warn!(
+                "Browser fetch failed, retrying in a new tab: {}: {}",
+                url,
+                e
+            );
+            attempt()
+        }
+        result => result,
+    }
+}
+
+/// Opens a tab, reporting a closed connection as `BrowserClosed` so that the
+/// browser is relaunched. The tab only loads documents from public hosts.
+pub(crate) fn new_tab(browser: &headless_chrome::Browser) -> Result<Arc<Tab>, Error> {
+    let tab = browser.new_tab().map_err(|e| -> Error {
+        if e.is::<headless_chrome::browser::transport::ConnectionClosed>() {
+            BrowserClosed.into()
+        } else {
+            e.into()
+        }
+    })?;
+
+    if let Err(e) = guard_tab(&tab) {
+        let _ = tab.close(false);
+        return Err(e);
+    }
+
+    Ok(tab)
+}
+
+/// Pauses every request of a tab until its target is checked: navigations
+/// and each hop of their redirects, but also the frames, scripts, images and
+/// `fetch()` calls of the page, which could otherwise reach internal hosts.
+/// Checking the URL before navigating isn't enough: the browser follows
+/// redirects by itself.
+fn guard_tab(tab: &Tab) -> Result<(), Error> {
+    let patterns = [RequestPattern {
+        url_pattern: None,
+        resource_Type: None,
+        request_stage: Some(RequestStage::Request),
+    }];
+
+    tab.enable_fetch(Some(&patterns), None)?;
+
+    tab.enable_request_interception(Arc::new(
+        |_transport, _session_id, event: RequestPausedEvent| {
+            request_decision(event.params.request_id, &event.params.request.url)
+        },
+    ))?;
+
+    Ok(())
+}
+
+/// Lets a request through, or fails it when its target is blocked.
+fn request_decision(request_id: String, url: &str) -> RequestPausedDecision {
+    // Content the page already holds reaches no host
+    if url.starts_with("data:") || url.starts_with("blob:") {
+        return RequestPausedDecision::Continue(None);
+    }
+
+    let checked = reqwest::Url::parse(url)
+        .map_err(Error::from)
+        .and_then(|url| crate::ssrf::check_target_blocking(&url));
+
+    match checked {
+        Ok(()) => RequestPausedDecision::Continue(None),
+        Err(e) => {
+            tracing::warn!("Browser request refused: {}", e);
+            RequestPausedDecision::Fail(FailRequest {
+                request_id,
+                error_reason: ErrorReason::BlockedByClient,
+            })
+        }
+    }
+}
+
+/// Checks where a navigation ended up, in case a redirect escaped the
+/// request interception of [`guard_tab`].
+pub(crate) fn check_navigated_url(tab: &Tab) -> Result<(), Error> {
+    let url = tab.get_url();
+
+    // A blank page yields nothing to leak
+    if url == "about:blank" {
+        return Ok(());
+    }
+
+    crate::ssrf::check_target_blocking(&reqwest::Url::parse(&url)?)
+}
+
+fn fetch_in_new_tab(
+    browser: &headless_chrome::Browser,
+    url: &str,
+    options: &FetchOptions,
+) -> Result<String, Error> {
+    let tab = new_tab(browser)?;
+
+    let result = fetch_in_tab(&tab, url, options);
+
+    let _ = tab.close(false);
+
+    result
+}
+
+/// Applies the `User-Agent` configured for the host and the configured
+/// `Accept-Language` to a tab, before navigating it to `url`.
+fn prepare_tab(tab: &Tab, url: &str, config: &crate::config::Config) -> Result<(), Error> {
+    if let Some(user_agent) = config.user_agent_for(url) {
+        tab.set_user_agent(user_agent, None, None)?;
+    }
+
+    // Same header as the reqwest path, so that both get the same localized content
+    if let Some(accept_language) = &config.fetch_accept_language {
+        tab.set_extra_http_headers(std::collections::HashMap::from([(
+            "Accept-Language",
+            accept_language.as_str(),
+        )]))?;
+    }
+
+    Ok(())
+}
+
+/// Renders a page and returns its final URL and HTML.
+pub(super) fn rendered_html(
+    browser: &headless_chrome::Browser,
+    url: &str,
+) -> Result<(String, String), Error> {
+    let tab = new_tab(browser)?;
+
+    let result = (|| -> Result<(String, String), Error> {
+        prepare_tab(&tab, url, crate::config::config())?;
+
+        tab.navigate_to(url)?;
+        check_navigated_url(&tab)?;
+
+        FlexibleWaiter::new(&tab).wait_smart()?;
+
+        Ok((tab.get_url(), tab.get_content()?))
+    })();
+
+    let _ = tab.close(false);
+
+    result
+}
+
+fn fetch_in_tab(tab: &Tab, url: &str, options: &FetchOptions) -> Result<String, Error> {
+    prepare_tab(tab, url, crate::config::config())?;
+
+    tab.navigate_to(url)?;
+    check_navigated_url(tab)?;
+
+    FlexibleWaiter::new(tab)
+        .with_timeout(options.timeout.unwrap_or(Duration::from_secs(15)))
+        .wait_smart()?;
+
+    if options.auto_accept_cookies {
+        let clicked = tab
+            .evaluate(ACCEPT_COOKIES_SCRIPT, false)?
+            .value
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        if clicked {
+            tracing::info!("Accepted cookie consent: {}", url);
+            // Let the overlay close and the content render
+            std::thread::sleep(Duration::from_millis(1000));
+        }
+    }
+
+    let html = match (&options.frame_name, &options.selector) {
+        (Some(name), selector) => {
+            let html = frame_html(tab, name, options)?;
+
+            match selector {
+                Some(selector) => super::convert::select_html(&html, selector)?,
+                None => html,
+            }
+        }
+        (None, Some(selector)) => tab
+            .find_element(selector)
+            .map_err(|_| super::convert::SelectorNotFound(selector.clone()))?
+            .get_content()?,
+        (None, None) => tab.wait_for_element("body")?.get_content()?,
+    };
+
+    Ok(super::convert::process_html(&html))
+}
+
+/// The connection to the browser was closed, e.g. after it sat idle.
+#[derive(Debug)]
+pub(super) struct BrowserClosed;
+
+impl std::fmt::Display for BrowserClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the connection to the browser is closed")
+    }
+}
+
+impl std::error::Error for BrowserClosed {}
+
+/// The browser binary exists but can't be driven: built for another
+/// architecture, or a version that doesn't complete the DevTools handshake.
+#[derive(Debug)]
+pub struct BrowserIncompatible {
+    pub path: std::path::PathBuf,
+    pub detail: String,
+}
+
+impl std::fmt::Display for BrowserIncompatible {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "BrowserIncompatible: {} could not be driven ({}). \
+             Expected chrome-headless-shell@stable built for this platform, \
+             as installed by `npx @puppeteer/browsers install chrome-headless-shell@stable`.",
+            self.path.display(),
+            self.detail
+        )
+    }
+}
+
+impl std::error::Error for BrowserIncompatible {}
+
+/// Tells an incompatible binary apart from other launch failures, such as a
+/// missing binary or no free debugging port.
+fn is_incompatible(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            // ENOEXEC: not an executable for this architecture
+            return io.raw_os_error() == Some(8);
+        }
+
+        // The process started but never spoke the DevTools protocol
+        cause.to_string().contains("didn't give us a WebSocket URL")
+    })
+}
+
+pub(super) fn launch_browser() -> Result<headless_chrome::Browser, Error> {
+    launch_browser_at(crate::config::config().chrome_executable()?)
+}
+
+fn launch_browser_at(path: std::path::PathBuf) -> Result<headless_chrome::Browser, Error> {
+    tracing::info!("Initializing browser: {}", path.display());
+
+    let incompatible = |detail: String| BrowserIncompatible {
+        path: path.clone(),
+        detail,
+    };
+
+    let browser = headless_chrome::Browser::new(headless_chrome::LaunchOptions {
+        headless: true,
+        sandbox: false,
+        devtools: false,
+        enable_gpu: false,
+        enable_logging: false,
+        path: Some(path.clone()),
+        args: vec![
+            &std::ffi::OsString::from("--disable-setuid-sandbox"),
+            &std::ffi::OsString::from("--disable-dev-shm-usage"),
+            &std::ffi::OsString::from("--disable-software-rasterizer"),
+            &std::ffi::OsString::from("--single-process"),
+            &std::ffi::OsString::from("--no-zygote"),
+        ],
+        ..Default::default()
+    })
+    .map_err(|e| -> Error {
+        if is_incompatible(&e) {
+            incompatible(e.to_string()).into()
+        } else {
+            e.into()
+        }
+    })?;
+
+    // A version too far from the protocol spoken by headless_chrome launches
+    // fine, then fails its first command
+    let version = browser
+        .get_version()
+        .map_err(|e| incompatible(format!("version handshake failed: {e}")))?;
+
+    tracing::info!("Browser ready: {}", version.product);
+
+    Ok(browser)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fails_requests_to_blocked_targets() {
+        for url in [
+            "http://127.0.0.1:8081/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://localhost/",
+            "file:///etc/passwd",
+            "not a url",
+        ] {
+            assert!(
+                matches!(
+                    request_decision("1".to_owned(), url),
+                    RequestPausedDecision::Fail(FailRequest {
+                        error_reason: ErrorReason::BlockedByClient,
+                        ..
+                    })
+                ),
+                "{url} should be blocked"
+            );
+        }
+    }
+
+    #[test]
+    fn continues_requests_to_public_targets() {
+        for url in [
+            "https://93.184.216.34/",
+            "data:image/png;base64,iVBORw0KGgo=",
+            "blob:https://example.com/1e2d",
+        ] {
+            assert!(
+                matches!(
+                    request_decision("1".to_owned(), url),
+                    RequestPausedDecision::Continue(None)
+                ),
+                "{url} should be allowed"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn blocks_subrequests_of_pages_to_internal_hosts() {
+        if !crate::test_support::browser_available() {
+            return;
+        }
+
+        let hits = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let internal = crate::test_support::serve_internal(axum::Router::new().route(
+            "/secret",
+            axum::routing::get({
+                let hits = hits.clone();
+                move || async move {
+                    hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    "secret"
+                }
+            }),
+        ))
+        .await;
+
+        // The page shows its text once the request settled, which the browser waits for
+        let page = format!(
+            r#"<html><body><img src="{internal}/secret"><script>
+            fetch("{internal}/secret").catch(() => {{}}).finally(() => {{
+                document.body.append("A public page requesting an internal service. ".repeat(10));
+            }});
+            </script></body></html>"#
+        );
+        let public = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(move || async move { axum::response::Html(page) }),
+        ))
+        .await;
+
+        super::super::with_browser(move |browser| rendered_html(browser, &public))
+            .await
+            .unwrap();
+
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    /// Serves `body` as a page and fetches it in the browser, accepting cookies.
+    async fn fetch_accepting_cookies(body: &'static str) -> Result<String, Error> {
+        let page = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(move || async move { axum::response::Html(body) }),
+        ))
+        .await;
+
+        let options = FetchOptions {
+            auto_accept_cookies: true,
+            ..Default::default()
+        };
+
+        super::super::with_browser(move |browser| fetch_in_new_tab(browser, &page, &options)).await
+    }
+
+    #[tokio::test]
+    async fn captures_the_content_behind_a_consent_overlay() {
+        if !crate::test_support::browser_available() {
+            return;
+        }
+
+        let markdown = fetch_accepting_cookies(
+            r#"<html><body>
+            <div id="cookie-banner">We use cookies. <button onclick="accept()">Accept all</button></div>
+            <main id="content"></main>
+            <script>
+            function accept() {
+                document.getElementById("cookie-banner").remove();
+                document.getElementById("content").textContent = "The article behind the overlay.";
+            }
+            </script>
+            </body></html>"#,
+        )
+        .await
+        .unwrap();
+
+        assert!(markdown.contains("The article behind the overlay."));
+        assert!(!markdown.contains("We use cookies"));
+    }
+
+    #[tokio::test]
+    async fn leaves_dialogs_outside_consent_containers_alone() {
+        if !crate::test_support::browser_available() {
+            return;
+        }
+
+        let markdown = fetch_accepting_cookies(
+            r#"<html><body>
+            <div id="newsletter" role="dialog">Subscribe? <button onclick="this.parentElement.textContent = 'Subscribed'">OK</button></div>
+            <main>The article.</main>
+            </body></html>"#,
+        )
+        .await
+        .unwrap();
+
+        assert!(markdown.contains("Subscribe?"));
+        assert!(!markdown.contains("Subscribed"));
+    }
+
+    #[tokio::test]
+    async fn sends_the_configured_accept_language() {
+        if !crate::test_support::browser_available() {
+            return;
+        }
+
+        let received = Arc::new(std::sync::Mutex::new(None));
+
+        let page = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get({
+                let received = received.clone();
+                move |headers: axum::http::HeaderMap| async move {
+                    *received.lock().unwrap() = headers
+                        .get("accept-language")
+                        .and_then(|value| value.to_str().ok())
+                        .map(str::to_owned);
+                    axum::response::Html("<html><body>Localized</body></html>")
+                }
+            }),
+        ))
+        .await;
+
+        let config = crate::config::Config {
+            fetch_accept_language: Some("ja-JP,ja;q=0.9".to_owned()),
+            ..crate::config::config().clone()
+        };
+
+        super::super::with_browser(move |browser| {
+            let tab = new_tab(browser)?;
+
+            let result = (|| -> Result<(), Error> {
+                prepare_tab(&tab, &page, &config)?;
+                tab.navigate_to(&page)?.wait_until_navigated()?;
+                Ok(())
+            })();
+
+            let _ = tab.close(false);
+            result
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(received.lock().unwrap().as_deref(), Some("ja-JP,ja;q=0.9"));
+    }
+
+    #[tokio::test]
+    async fn extracts_the_named_frame() {
+        if !crate::test_support::browser_available() {
+            return;
+        }
+
+        let page = crate::test_support::serve(
+            axum::Router::new()
+                .route(
+                    "/",
+                    axum::routing::get(|| async {
+                        axum::response::Html(
+                            r#"<html><body><p>The frameset page.</p><iframe name="content" src="/frame"></iframe></body></html>"#,
+                        )
+                    }),
+                )
+                .route(
+                    "/frame",
+                    axum::routing::get(|| async {
+                        axum::response::Html("<html><body><p>The framed article.</p></body></html>")
+                    }),
+                ),
+        )
+        .await;
+
+        let fetch = |frame_name: &str| {
+            let page = page.clone();
+            let options = FetchOptions {
+                frame_name: Some(frame_name.to_owned()),
+                ..Default::default()
+            };
+
+            super::super::with_browser(move |browser| fetch_in_new_tab(browser, &page, &options))
+        };
+
+        let markdown = fetch("content").await.unwrap();
+        assert!(markdown.contains("The framed article."), "{}", markdown);
+        assert!(!markdown.contains("The frameset page."), "{}", markdown);
+
+        let error = fetch("missing").await.unwrap_err();
+        assert!(error.is::<FrameNotFound>(), "{}", error);
+    }
+
+    /// Runs [`retry_in_new_tab`] over attempts yielding `results` in turn,
+    /// returning the outcome and the number of attempts.
+    fn retry(
+        mut results: Vec<Result<&'static str, Error>>,
+    ) -> (Result<&'static str, Error>, usize) {
+        let mut attempts = 0;
+
+        let result = retry_in_new_tab("https://example.com/", || {
+            attempts += 1;
+            results.remove(0)
+        });
+
+        (result, attempts)
+    }
+
+    #[test]
+    fn retries_a_failed_tab_once_in_a_new_one() {
+        let (result, attempts) = retry(vec![Err("renderer crashed".into()), Ok("page")]);
+        assert_eq!(result.unwrap(), "page");
+        assert_eq!(attempts, 2);
+
+        let (result, attempts) = retry(vec![
+            Err("renderer crashed".into()),
+            Err("renderer crashed again".into()),
+            Ok("page"),
+        ]);
+        assert_eq!(result.unwrap_err().to_string(), "renderer crashed again");
+        assert_eq!(attempts, 2);
+    }
+
+    #[test]
+    fn gives_up_on_errors_a_new_tab_cant_fix() {
+        for error in [
+            Error::from(BrowserClosed),
+            FrameNotFound("main".to_owned()).into(),
+            super::super::convert::SelectorNotFound("#content".to_owned()).into(),
+        ] {
+            let (result, attempts) = retry(vec![Err(error), Ok("page")]);
+            assert!(result.is_err());
+            assert_eq!(attempts, 1);
+        }
+    }
+
+    #[test]
+    fn tells_incompatible_binaries_apart() {
+        let wrong_architecture = anyhow::Error::new(std::io::Error::from_raw_os_error(8));
+        let no_handshake = anyhow::anyhow!(
+            "Chrome launched, but didn't give us a WebSocket URL before we timed out"
+        );
+        let missing = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::NotFound));
+
+        assert!(is_incompatible(&wrong_architecture));
+        assert!(is_incompatible(&no_handshake.context("launching")));
+        assert!(!is_incompatible(&missing));
+        assert!(!is_incompatible(&anyhow::anyhow!("no free port")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_binaries_that_cant_be_executed() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // An executable file in no format the kernel knows, as a binary for another architecture is
+        let path = std::env::temp_dir().join(format!("not-chrome-{}", std::process::id()));
+        std::fs::write(&path, [0x7f, b'X', b'Y', b'Z', 0, 1, 2, 3]).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let error = launch_browser_at(path.clone()).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let incompatible = error.downcast_ref::<BrowserIncompatible>().unwrap();
+        assert_eq!(incompatible.path, path);
+        assert!(error.to_string().contains("chrome-headless-shell@stable"));
+    }
+}
@@ -0,0 +1,698 @@
+use std::sync::LazyLock;
+
+use tokio::sync::Semaphore;
+
+use super::{Error, math};
+
+/// Bounds the conversions running at once on the blocking pool.
+static CONVERT_PERMITS: LazyLock<Semaphore> =
+    LazyLock::new(|| Semaphore::new(crate::config::config().convert_max_concurrency));
+
+/// Runs a CPU-bound conversion on tokio's blocking pool, so that converting
+/// a large page doesn't stall the other requests served by the runtime.
+pub(crate) async fn offload<T, F>(job: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let _permit = CONVERT_PERMITS.acquire().await?;
+
+    Ok(tokio::task::spawn_blocking(job).await?)
+}
+
+/// Process HTML to markdown
+pub(crate) fn process_html(html: &str) -> String {
+    match math::extract_math(html) {
+        Some((html, equations)) => {
+            math::restore_math(&html2md::rewrite_html(&html, false), &equations)
+        }
+        None => html2md::rewrite_html(html, false),
+    }
+}
+
+/// Strips the markdown syntax, keeping only the text.
+pub(crate) fn markdown_to_text(markdown: &str) -> String {
+    use pulldown_cmark::{Event, Parser, TagEnd};
+
+    let mut text = String::with_capacity(markdown.len());
+
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(t) | Event::Code(t) | Event::InlineMath(t) | Event::DisplayMath(t) => {
+                text.push_str(&t)
+            }
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::Item
+                | TagEnd::CodeBlock
+                | TagEnd::TableHead
+                | TagEnd::TableRow,
+            ) => text.push('\n'),
+            Event::End(TagEnd::TableCell) => text.push('\t'),
+            _ => {}
+        }
+    }
+
+    text.trim().to_owned()
+}
+
+/// Decodes a response body to UTF-8.
+///
+/// The charset declared in the `Content-Type` header wins, then the one
+/// declared by a `<meta>` tag. When neither is present, the charset is
+/// detected statistically from the bytes.
+pub(super) fn decode_body(content_type: &str, bytes: &[u8]) -> String {
+    let declared = charset_label(content_type)
+        .or_else(|| {
+            let head = &bytes[..bytes.len().min(1024)];
+            charset_label(&String::from_utf8_lossy(head))
+        })
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()));
+
+    let encoding = declared.unwrap_or_else(|| {
+        let mut detector = chardetng::EncodingDetector::new(chardetng::Iso2022JpDetection::Deny);
+        detector.feed(bytes, true);
+        detector.guess(None, chardetng::Utf8Detection::Allow)
+    });
+
+    // A byte order mark, if any, still takes precedence
+    let (text, _, _) = encoding.decode(bytes);
+
+    text.into_owned()
+}
+
+/// Finds a `charset=<label>` parameter, as found in `Content-Type` values
+/// and `<meta charset>` / `<meta http-equiv>` tags.
+fn charset_label(haystack: &str) -> Option<String> {
+    let lower = haystack.to_ascii_lowercase();
+    let start = lower.find("charset")? + "charset".len();
+
+    let label = lower[start..]
+        .trim_start()
+        .strip_prefix('=')?
+        .trim_start()
+        .trim_start_matches(['"', '\''])
+        .split(|c: char| c == '"' || c == '\'' || c == ';' || c == '>' || c.is_whitespace())
+        .next()?
+        .to_owned();
+
+    (!label.is_empty()).then_some(label)
+}
+
+/// Converts a response body to text according to its `Content-Type`.
+/// JSON is pretty-printed, HTML is converted to markdown and other
+/// text types are returned as-is.
+pub(super) fn convert_body(content_type: &str, body: &str) -> String {
+    match essence(content_type).as_str() {
+        "application/json" => pretty_json(body),
+        e if e.ends_with("+json") => pretty_json(body),
+        "" | "text/html" | "application/xhtml+xml" => process_html(body),
+        e if e.starts_with("text/") => body.to_owned(),
+        _ => process_html(body),
+    }
+}
+
+/// The lowercase media type of a `Content-Type`, without parameters.
+pub(super) fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Whether a media type is text that can be converted, as opposed to binary
+/// content such as a PDF or an image.
+pub(super) fn is_textual(essence: &str) -> bool {
+    matches!(
+        essence,
+        "" | "application/json"
+            | "application/xml"
+            | "application/javascript"
+            | "application/ecmascript"
+            | "application/yaml"
+            | "application/x-yaml"
+            | "application/toml"
+    ) || essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+}
+
+pub(super) fn is_html(content_type: &str) -> bool {
+    matches!(
+        essence(content_type).as_str(),
+        "" | "text/html" | "application/xhtml+xml"
+    )
+}
+
+/// Converts the `<noscript>` blocks of a page to markdown. They hold the
+/// content sites provide to clients without JavaScript, which the regular
+/// conversion drops.
+pub(super) fn noscript_markdown(html: &str) -> Option<String> {
+    let document = scraper::Html::parse_document(html);
+    let selector = scraper::Selector::parse("noscript").unwrap();
+
+    // Parsed with scripting enabled, `<noscript>` holds its markup as raw text
+    let markdown = document
+        .select(&selector)
+        .map(|noscript| process_html(&noscript.text().collect::<String>()))
+        .map(|markdown| markdown.trim().to_owned())
+        .filter(|markdown| !markdown.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    (!markdown.is_empty()).then_some(markdown)
+}
+
+/// No element of the page matches the requested selector.
+#[derive(Debug)]
+pub(super) struct SelectorNotFound(pub String);
+
+impl std::fmt::Display for SelectorNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the selector {:?} matched nothing on the page", self.0)
+    }
+}
+
+impl std::error::Error for SelectorNotFound {}
+
+/// Returns the HTML of the first element matching `selector`.
+pub(super) fn select_html(html: &str, selector: &str) -> Result<String, Error> {
+    let document = scraper::Html::parse_document(html);
+    let parsed = scraper::Selector::parse(selector)
+        .map_err(|e| format!("invalid selector {:?}: {}", selector, e))?;
+
+    document
+        .select(&parsed)
+        .next()
+        .map(|element| element.html())
+        .ok_or_else(|| SelectorNotFound(selector.to_owned()).into())
+}
+
+fn pretty_json(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .unwrap_or_else(|_| body.to_owned())
+}
+
+/// Upper bound of a decompressed file, guarding against gzip bombs.
+const MAX_DECOMPRESSED_BYTES: u64 = 10 * 1024 * 1024;
+
+pub(super) fn is_gzip_file(content_type: &str, path: &str, bytes: &[u8]) -> bool {
+    is_declared_gzip(content_type, path) && bytes.starts_with(&[0x1f, 0x8b])
+}
+
+/// Whether a response claims to be a gzipped file, by its type or extension.
+pub(super) fn is_declared_gzip(content_type: &str, path: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+
+    essence.eq_ignore_ascii_case("application/gzip")
+        || essence.eq_ignore_ascii_case("application/x-gzip")
+        || path.to_ascii_lowercase().ends_with(".gz")
+}
+
+/// Guesses the content type of a gzipped file from the extension under `.gz`.
+pub(super) fn inner_content_type(path: &str) -> &'static str {
+    let path = path.to_ascii_lowercase();
+    let inner = path.strip_suffix(".gz").unwrap_or(&path);
+
+    match inner.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("html" | "htm") => "text/html",
+        Some("json") => "application/json",
+        Some("xml") => "text/xml",
+        Some("csv") => "text/csv",
+        Some("md" | "markdown") => "text/markdown",
+        _ => "text/plain",
+    }
+}
+
+pub(super) fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    use std::io::Read;
+
+    let mut decompressed = Vec::new();
+
+    let result = flate2::read::GzDecoder::new(bytes)
+        .take(MAX_DECOMPRESSED_BYTES)
+        .read_to_end(&mut decompressed);
+
+    match result {
+        Ok(_) => Ok(decompressed),
+        // A file cut by `FETCH_MAX_BYTES` still yields its beginning
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof && !decompressed.is_empty() => {
+            Ok(decompressed)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Splits markdown into chunks of at most `size` characters, cutting at
+/// paragraph boundaries and preferring to start chunks at headings. Blocks
+/// longer than `size` are cut at line breaks, or mid-line as a last resort.
+pub(super) fn chunk_markdown(markdown: &str, size: usize) -> Vec<String> {
+    let size = size.max(1);
+
+    let mut chunks = vec![];
+    let mut current = String::new();
+
+    let blocks = markdown
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|block| !block.is_empty())
+        .flat_map(|block| split_block(block, size));
+
+    for block in blocks {
+        let length = current.chars().count();
+        let separator = if current.is_empty() { 0 } else { 2 };
+
+        let overflows = length + separator + block.chars().count() > size;
+        let heading_break = block.starts_with('#') && length >= size / 2;
+
+        if !current.is_empty() && (overflows || heading_break) {
+            chunks.push(std::mem::take(&mut current));
+        }
+
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(&block);
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Splits a block longer than `size` characters into pieces that fit.
+fn split_block(block: &str, size: usize) -> Vec<String> {
+    if block.chars().count() <= size {
+        return vec![block.to_owned()];
+    }
+
+    let mut pieces = vec![];
+    let mut current = String::new();
+
+    for line in block.lines() {
+        let mut line = line.to_owned();
+
+        while line.chars().count() > size {
+            let cut = line
+                .char_indices()
+                .nth(size)
+                .map(|(index, _)| index)
+                .unwrap_or(line.len());
+            let rest = line.split_off(cut);
+            pieces.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+            pieces.push(line);
+            line = rest;
+        }
+
+        let separator = if current.is_empty() { 0 } else { 1 };
+
+        if current.chars().count() + separator + line.chars().count() > size {
+            pieces.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+        }
+
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+
+    pieces
+}
+
+/// Extracts the section under the heading that best matches `name`, up to the
+/// next heading of the same or a higher level. Headings are matched
+/// case-insensitively and loosely (e.g. `install` matches `## Installation`).
+pub(super) fn extract_section(markdown: &str, name: &str) -> Option<String> {
+    let headings = headings(markdown);
+    let wanted = normalize_heading(name);
+
+    let (position, _) = headings
+        .iter()
+        .enumerate()
+        .filter_map(|(position, heading)| {
+            Some((
+                position,
+                heading_score(&normalize_heading(&heading.text), &wanted)?,
+            ))
+        })
+        .max_by_key(|&(position, score)| (score, std::cmp::Reverse(position)))?;
+
+    let heading = &headings[position];
+
+    let end = headings[position + 1..]
+        .iter()
+        .find(|next| next.level <= heading.level)
+        .map_or(markdown.len(), |next| next.offset);
+
+    Some(markdown[heading.offset..end].trim().to_owned())
+}
+
+/// Lists the text of the headings of a markdown document.
+pub(super) fn heading_texts(markdown: &str) -> Vec<String> {
+    headings(markdown)
+        .into_iter()
+        .map(|heading| heading.text)
+        .collect()
+}
+
+struct Heading {
+    level: usize,
+    text: String,
+    /// Byte offset of the heading line.
+    offset: usize,
+}
+
+/// Finds the ATX headings (`## Title`) outside of fenced code blocks.
+fn headings(markdown: &str) -> Vec<Heading> {
+    let mut headings = vec![];
+    let mut in_fence = false;
+    let mut offset = 0;
+
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            let level = trimmed.chars().take_while(|&c| c == '#').count();
+            let rest = &trimmed[level..];
+
+            if (1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')) {
+                headings.push(Heading {
+                    level,
+                    text: markdown_to_text(rest.trim()),
+                    offset,
+                });
+            }
+        }
+
+        offset += line.len();
+    }
+
+    headings
+}
+
+/// Lowercases and keeps only the words of a heading, dropping anchors and punctuation.
+fn normalize_heading(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Scores how well a normalized heading matches the normalized name, if at all.
+fn heading_score(heading: &str, wanted: &str) -> Option<u8> {
+    if heading.is_empty() || wanted.is_empty() {
+        return None;
+    }
+
+    if heading == wanted {
+        return Some(4);
+    }
+
+    if heading.starts_with(wanted) || wanted.starts_with(heading) {
+        return Some(3);
+    }
+
+    if heading.contains(wanted) {
+        return Some(2);
+    }
+
+    // Tolerates typos and small spelling differences
+    let tolerance = (wanted.chars().count() / 4).max(1);
+    (levenshtein(heading, wanted) <= tolerance).then_some(1)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_by_content_type() {
+        assert_eq!(
+            convert_body("application/json; charset=utf-8", r#"{"a":1}"#),
+            "{\n  \"a\": 1\n}"
+        );
+        assert_eq!(convert_body("application/ld+json", "[1]"), "[\n  1\n]");
+        // Invalid JSON is returned untouched
+        assert_eq!(convert_body("application/json", "{"), "{");
+        assert_eq!(convert_body("text/plain", "<b>as is</b>"), "<b>as is</b>");
+        assert_eq!(
+            convert_body("text/html", "<h2>Title</h2>").trim(),
+            "## Title"
+        );
+        assert_eq!(convert_body("", "<h2>Title</h2>").trim(), "## Title");
+    }
+
+    #[test]
+    fn tells_text_from_binary_types() {
+        assert!(is_textual("text/csv"));
+        assert!(is_textual("application/json"));
+        assert!(is_textual("application/atom+xml"));
+        assert!(is_textual(""));
+        assert!(!is_textual("application/pdf"));
+        assert!(!is_textual("image/png"));
+        assert!(!is_textual("application/octet-stream"));
+    }
+
+    #[test]
+    fn recognizes_html() {
+        assert!(is_html("text/html; charset=utf-8"));
+        assert!(is_html("application/xhtml+xml"));
+        assert!(is_html(""));
+        assert!(!is_html("text/plain"));
+    }
+
+    #[test]
+    fn detects_undeclared_shift_jis() {
+        let text =
+            "<p>日本語のページです。文字コードは宣言されていませんが、内容から判別できます。</p>";
+        let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(text);
+
+        assert_eq!(decode_body("text/html", &bytes), text);
+    }
+
+    #[test]
+    fn prefers_declared_charsets() {
+        let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode("café");
+
+        assert_eq!(
+            decode_body("text/plain; charset=windows-1252", &bytes),
+            "café"
+        );
+
+        let mut html = b"<meta charset=\"windows-1252\">".to_vec();
+        html.extend_from_slice(&bytes);
+        assert!(decode_body("text/html", &html).ends_with("café"));
+    }
+
+    #[test]
+    fn finds_charset_labels() {
+        assert_eq!(
+            charset_label("text/html; charset=Shift_JIS").as_deref(),
+            Some("shift_jis")
+        );
+        assert_eq!(
+            charset_label(
+                r#"<meta http-equiv="Content-Type" content="text/html; charset='euc-jp'">"#
+            )
+            .as_deref(),
+            Some("euc-jp")
+        );
+        assert_eq!(charset_label("text/html"), None);
+    }
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn caps_decompressed_size() {
+        let bomb = gzip(&vec![0; MAX_DECOMPRESSED_BYTES as usize * 2]);
+
+        let decompressed = gunzip(&bomb).unwrap();
+
+        assert_eq!(decompressed.len() as u64, MAX_DECOMPRESSED_BYTES);
+    }
+
+    #[test]
+    fn gunzips_truncated_files() {
+        let text = "line of text\n".repeat(10_000);
+        let compressed = gzip(text.as_bytes());
+
+        let decompressed = gunzip(&compressed[..compressed.len() / 2]).unwrap();
+
+        assert!(!decompressed.is_empty());
+        assert!(text.as_bytes().starts_with(&decompressed));
+    }
+
+    #[test]
+    fn recognizes_gzip_files() {
+        let compressed = gzip(b"{}");
+
+        assert!(is_gzip_file("application/gzip", "/data", &compressed));
+        assert!(is_gzip_file(
+            "application/octet-stream",
+            "/data.json.gz",
+            &compressed
+        ));
+        assert!(!is_gzip_file("application/gzip", "/data", b"{}"));
+        assert!(!is_gzip_file("text/plain", "/data.json", &compressed));
+
+        assert_eq!(inner_content_type("/data.JSON.gz"), "application/json");
+        assert_eq!(inner_content_type("/archive.gz"), "text/plain");
+    }
+
+    #[test]
+    fn converts_noscript_blocks() {
+        let html = "<body><p>Enable JavaScript</p>\
+                    <noscript><h2>Menu</h2></noscript>\
+                    <noscript> </noscript>\
+                    <noscript><p>Soup of the day</p></noscript></body>";
+
+        assert_eq!(
+            noscript_markdown(html).as_deref(),
+            Some("## Menu\n\nSoup of the day")
+        );
+        assert_eq!(noscript_markdown("<p>No fallback</p>"), None);
+    }
+
+    #[test]
+    fn chunks_at_paragraph_boundaries() {
+        let markdown = "first paragraph\n\nsecond paragraph\n\nthird paragraph";
+
+        assert_eq!(
+            chunk_markdown(markdown, 40),
+            ["first paragraph\n\nsecond paragraph", "third paragraph"]
+        );
+    }
+
+    #[test]
+    fn starts_chunks_at_headings() {
+        let markdown = "an introduction of some length\n\n## Heading\n\nbody";
+
+        assert_eq!(
+            chunk_markdown(markdown, 60),
+            ["an introduction of some length", "## Heading\n\nbody"]
+        );
+        // A heading right after a short chunk joins it
+        assert_eq!(chunk_markdown(markdown, 80), [markdown]);
+    }
+
+    #[test]
+    fn splits_long_blocks() {
+        let chunks = chunk_markdown("line one\nline two\n0123456789abcdef", 10);
+
+        assert_eq!(chunks, ["line one", "line two", "0123456789", "abcdef"]);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 10));
+    }
+
+    #[test]
+    fn cuts_multibyte_text_at_char_boundaries() {
+        let chunks = chunk_markdown(&"日本語".repeat(5), 4);
+
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 4));
+        assert_eq!(chunks.concat(), "日本語".repeat(5));
+    }
+
+    const README: &str = "# Project\n\nIntro\n\n## Installation\n\nRun it.\n\n### From source\n\nBuild it.\n\n```sh\n# not a heading\n```\n\n## Usage\n\nUse it.\n";
+
+    #[test]
+    fn extracts_sections_up_to_the_next_sibling() {
+        assert_eq!(
+            extract_section(README, "install").as_deref(),
+            Some(
+                "## Installation\n\nRun it.\n\n### From source\n\nBuild it.\n\n```sh\n# not a heading\n```"
+            )
+        );
+        assert_eq!(
+            extract_section(README, "USAGE").as_deref(),
+            Some("## Usage\n\nUse it.")
+        );
+    }
+
+    #[test]
+    fn matches_headings_loosely() {
+        // Substring, then typos
+        assert_eq!(
+            extract_section(README, "source").as_deref(),
+            Some("### From source\n\nBuild it.\n\n```sh\n# not a heading\n```")
+        );
+        assert!(
+            extract_section(README, "instalation")
+                .unwrap()
+                .starts_with("## Installation")
+        );
+        assert_eq!(extract_section(README, "license"), None);
+        assert_eq!(extract_section(README, "not a heading"), None);
+    }
+
+    #[test]
+    fn prefers_exact_matches() {
+        let markdown = "## Usage notes\n\nNotes.\n\n## Usage\n\nUse it.";
+
+        assert_eq!(
+            extract_section(markdown, "usage").as_deref(),
+            Some("## Usage\n\nUse it.")
+        );
+        assert_eq!(heading_texts(markdown), ["Usage notes", "Usage"]);
+    }
+
+    #[tokio::test]
+    async fn converts_large_pages_without_blocking_other_requests() {
+        let base = crate::test_support::serve(
+            axum::Router::new().route("/", axum::routing::get(|| async { "fast" })),
+        )
+        .await;
+
+        let html = format!(
+            "<html><body>{}</body></html>",
+            "<p>A paragraph of <b>text</b> with a <a href=\"/x\">link</a>.</p>".repeat(20_000)
+        );
+
+        // The test runtime has a single thread, which an inline conversion would hold
+        let conversion = tokio::spawn(offload(move || process_html(&html)));
+        let request = async { reqwest::get(base).await.unwrap().text().await.unwrap() };
+
+        tokio::select! {
+            text = request => assert_eq!(text, "fast"),
+            _ = conversion => panic!("the conversion finished before the concurrent request"),
+        }
+    }
+}
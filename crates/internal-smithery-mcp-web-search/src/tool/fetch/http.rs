@@ -0,0 +1,855 @@
+use std::{borrow::Cow, time::Duration};
+
+use super::{Download, Error, FetchOptions, Truncation, convert};
+
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; internal-smithery-mcp/0.1)";
+
+/// Builds the HTTP client shared by the tools that fetch pages directly.
+pub(crate) fn http_client() -> Result<reqwest::Client, Error> {
+    let client = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(Duration::from_secs(10))
+        .redirect(crate::ssrf::redirect_policy())
+        .dns_resolver(std::sync::Arc::new(crate::ssrf::PublicResolver))
+        .build()?;
+
+    Ok(client)
+}
+
+/// Starts a GET request, with the `User-Agent` configured for the host and
+/// the configured `Accept-Language`, if any.
+pub(crate) fn get(client: &reqwest::Client, url: &str) -> reqwest::RequestBuilder {
+    request(client, reqwest::Method::GET, url)
+}
+
+/// Starts a HEAD request, with the same headers as [`get`].
+pub(crate) fn head(client: &reqwest::Client, url: &str) -> reqwest::RequestBuilder {
+    request(client, reqwest::Method::HEAD, url)
+}
+
+fn request(
+    client: &reqwest::Client,
+    method: reqwest::Method,
+    url: &str,
+) -> reqwest::RequestBuilder {
+    let config = crate::config::config();
+
+    let mut request = client.request(method, url);
+
+    if let Some(user_agent) = config.user_agent_for(url) {
+        request = request.header(reqwest::header::USER_AGENT, user_agent);
+    }
+
+    if let Some(accept_language) = &config.fetch_accept_language {
+        request = request.header(reqwest::header::ACCEPT_LANGUAGE, accept_language);
+    }
+
+    request
+}
+
+/// A response whose content type is not in `FETCH_ALLOWED_CONTENT_TYPES`.
+#[derive(Debug)]
+pub(super) struct DisallowedContentType(pub String);
+
+impl std::fmt::Display for DisallowedContentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Content type {} is not allowed on this server", self.0)
+    }
+}
+
+impl std::error::Error for DisallowedContentType {}
+
+/// A response sent with `Content-Disposition: attachment`, i.e. a file to
+/// download rather than a page to read.
+#[derive(Debug)]
+pub(super) struct Attachment(pub Download);
+
+impl std::fmt::Display for Attachment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Download {
+            filename,
+            content_type,
+            size,
+        } = &self.0;
+
+        write!(f, "The URL is a downloadable file")?;
+
+        if let Some(filename) = filename {
+            write!(f, ": {}", filename)?;
+        }
+
+        match (content_type, size) {
+            (Some(content_type), Some(size)) => write!(f, " ({}, {} bytes)", content_type, size),
+            (Some(content_type), None) => write!(f, " ({})", content_type),
+            (None, Some(size)) => write!(f, " ({} bytes)", size),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for Attachment {}
+
+/// Describes the file of a response sent with `Content-Disposition:
+/// attachment`, or `None` when the response is meant to be displayed.
+fn attachment(response: &reqwest::Response, essence: &str) -> Option<Download> {
+    let disposition = response
+        .headers()
+        .get(reqwest::header::CONTENT_DISPOSITION)?
+        .to_str()
+        .ok()?;
+
+    let mut parameters = disposition.split(';').map(str::trim);
+
+    if !parameters.next()?.eq_ignore_ascii_case("attachment") {
+        return None;
+    }
+
+    let mut filename = None;
+    let mut extended_filename = None;
+
+    for parameter in parameters {
+        let Some((name, value)) = parameter.split_once('=') else {
+            continue;
+        };
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "filename" => filename = Some(value.trim().trim_matches('"').to_owned()),
+            // RFC 5987, e.g. `UTF-8''na%C3%AFve.pdf`
+            "filename*" => {
+                extended_filename = value.trim().splitn(3, '\'').nth(2).map(percent_decode)
+            }
+            _ => {}
+        }
+    }
+
+    Some(download(response, essence, extended_filename.or(filename)))
+}
+
+/// Describes the file of a response, named after the last segment of its
+/// URL unless `filename` is given.
+fn download(response: &reqwest::Response, essence: &str, filename: Option<String>) -> Download {
+    let filename = filename.or_else(|| {
+        response
+            .url()
+            .path_segments()?
+            .next_back()
+            .filter(|segment| !segment.is_empty())
+            .map(percent_decode)
+    });
+
+    Download {
+        filename: filename.filter(|filename| !filename.is_empty()),
+        content_type: (!essence.is_empty()).then(|| essence.to_owned()),
+        size: response.content_length(),
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Whether a media type matches one of the allowed patterns, where `*`
+/// matches any run of characters. A missing type is treated as HTML.
+fn is_allowed_content_type(essence: &str, allowed: &[String]) -> bool {
+    let essence = if essence.is_empty() {
+        "text/html"
+    } else {
+        essence
+    };
+
+    allowed.iter().any(|pattern| {
+        let pattern = pattern.to_ascii_lowercase();
+
+        match pattern.split_once('*') {
+            Some((prefix, suffix)) => {
+                essence.len() >= prefix.len() + suffix.len()
+                    && essence.starts_with(prefix)
+                    && essence.ends_with(suffix)
+            }
+            None => essence == pattern,
+        }
+    })
+}
+
+/// A page fetched with reqwest, converted according to its content type.
+pub(super) struct Page {
+    pub status: reqwest::StatusCode,
+    pub content: String,
+    pub cache_policy: crate::cache::CachePolicy,
+    pub truncation: Option<Truncation>,
+    /// The `Retry-After` delay of a rate-limited response.
+    pub retry_after: Option<Duration>,
+}
+
+pub(super) async fn fetch_with_reqwest(
+    client: &reqwest::Client,
+    url: &str,
+    options: &FetchOptions,
+) -> Result<Page, Error> {
+    tracing::info!("Fetching with reqwest: {}", url);
+
+    let sending = crate::config::config().retry.retry(|| {
+        let mut request = get(client, url);
+
+        if let Some(accept) = &options.accept {
+            request = request.header(reqwest::header::ACCEPT, accept);
+        }
+
+        if let Some(timeout) = options.timeout {
+            request = request.timeout(timeout);
+        }
+
+        request.send()
+    });
+
+    // The timeout bounds the retries as a whole, not each attempt
+    let response = match options.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, sending)
+            .await
+            .map_err(|_| format!("Timed out after {:?} fetching {}", timeout, url))??,
+        None => sending.await?,
+    };
+
+    let status = response.status();
+
+    let cache_policy = crate::cache::CachePolicy::from_headers(response.headers());
+
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_retry_after);
+
+    // Error pages are never used as content, so their body is not converted
+    if !status.is_success() {
+        return Ok(Page {
+            status,
+            content: String::new(),
+            cache_policy,
+            truncation: None,
+            retry_after,
+        });
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+
+    let essence = convert::essence(&content_type);
+
+    if !crate::config::config().fetch_convert_attachments
+        && let Some(download) = attachment(&response, &essence)
+    {
+        tracing::info!("Not converting attachment: {}", url);
+        return Err(Attachment(download).into());
+    }
+
+    if !is_allowed_content_type(
+        &essence,
+        &crate::config::config().fetch_allowed_content_types,
+    ) {
+        return Err(DisallowedContentType(essence).into());
+    }
+
+    let path = response.url().path().to_owned();
+
+    // Binary types, e.g. PDF, would only be converted to garbage
+    if !convert::is_textual(&essence) && !convert::is_declared_gzip(&essence, &path) {
+        tracing::info!("Not converting {} file: {}", essence, url);
+        return Err(Attachment(download(&response, &essence, None)).into());
+    }
+
+    let content_length = response.content_length();
+
+    let (bytes, truncated) = read_body(response, crate::config::config().fetch_max_bytes).await?;
+
+    crate::metrics::add(
+        "fetch_received_bytes_total",
+        &[("tool", "fetch")],
+        bytes.len() as f64,
+    );
+
+    let truncation = truncated.then(|| {
+        tracing::warn!("Response body truncated to {} bytes: {}", bytes.len(), url);
+
+        Truncation {
+            original_length: content_length.and_then(|length| usize::try_from(length).ok()),
+            returned_length: bytes.len(),
+        }
+    });
+
+    let content = {
+        let url = url.to_owned();
+        let min_content_length = options.min_content_length;
+        let selector = options.selector.clone();
+
+        convert::offload(move || {
+            convert_response(
+                &url,
+                &content_type,
+                &path,
+                &bytes,
+                min_content_length,
+                selector.as_deref(),
+            )
+        })
+        .await??
+    };
+
+    Ok(Page {
+        status,
+        content,
+        cache_policy,
+        truncation,
+        retry_after,
+    })
+}
+
+/// Decompresses, decodes and converts a response body. CPU-bound on large
+/// pages, hence run off the async runtime.
+fn convert_response(
+    url: &str,
+    content_type: &str,
+    path: &str,
+    bytes: &[u8],
+    min_content_length: usize,
+    selector: Option<&str>,
+) -> Result<String, Error> {
+    // Transport compression is already undone by reqwest; gzip magic bytes that
+    // remain belong to a genuinely gzipped file, e.g. `data.json.gz`
+    let (content_type, bytes) = if convert::is_gzip_file(content_type, path, bytes) {
+        tracing::info!("Decompressing gzipped file: {}", url);
+        (
+            convert::inner_content_type(path),
+            Cow::Owned(convert::gunzip(bytes)?),
+        )
+    } else {
+        (content_type, Cow::Borrowed(bytes))
+    };
+
+    let body = convert::decode_body(content_type, &bytes);
+
+    // Only the selected subtree of a page is converted. A selector matching
+    // nothing fails the attempt, as the element may be rendered by JavaScript
+    if let Some(selector) = selector
+        && convert::is_html(content_type)
+    {
+        let html = convert::select_html(&body, selector)?;
+        return Ok(convert::process_html(&html));
+    }
+
+    let mut content = convert::convert_body(content_type, &body);
+
+    // The non-JavaScript fallback of a page may spare rendering it in the browser
+    if content.chars().count() < min_content_length
+        && convert::is_html(content_type)
+        && let Some(noscript) = convert::noscript_markdown(&body)
+    {
+        tracing::info!("Using <noscript> content: {}", url);
+        if !content.trim().is_empty() {
+            content.push_str("\n\n");
+        }
+        content.push_str(&noscript);
+    }
+
+    Ok(content)
+}
+
+/// Parses `Retry-After`, given either in seconds or as an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+
+    Some(
+        date.duration_since(std::time::SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+/// Fetches the raw HTML of a page, for the tools extracting structure from it,
+/// or any other text they parse themselves, such as `robots.txt`. Also returns
+/// the final URL after redirects, which relative links resolve against.
+pub(crate) async fn fetch_html(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<(reqwest::Url, String), Error> {
+    tracing::info!("Fetching HTML: {}", url);
+
+    let target = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL {:?}: {}", url, e))?;
+    crate::ssrf::check_target(&target).await?;
+
+    let _permit = super::fetch_permit().await?;
+
+    let response = crate::config::config()
+        .retry
+        .retry(|| get(client, url).send())
+        .await?
+        .error_for_status()?;
+
+    let final_url = response.url().clone();
+
+    Ok((final_url, read_text(response).await?))
+}
+
+/// Reads at most `FETCH_MAX_BYTES` of a body and decodes it to UTF-8.
+async fn read_text(response: reqwest::Response) -> Result<String, Error> {
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+
+    let (bytes, truncated) = read_body(response, crate::config::config().fetch_max_bytes).await?;
+
+    if truncated {
+        tracing::warn!("Response body truncated to {} bytes", bytes.len());
+    }
+
+    Ok(convert::decode_body(&content_type, &bytes))
+}
+
+/// Reads the body chunk by chunk, stopping once `max_bytes` are read so that
+/// huge pages are never held in memory in full. Returns whether it was cut.
+///
+/// Bytes are counted as they stream in, so the guard doesn't depend on
+/// `Content-Length`, which legacy and HTTP/1.0 servers often omit. Such
+/// servers may also end the body by dropping the connection: once some bytes
+/// were read, a connection lost mid-body yields what was received, reported
+/// as cut.
+async fn read_body(
+    mut response: reqwest::Response,
+    max_bytes: usize,
+) -> Result<(bytes::Bytes, bool), Error> {
+    let mut body = bytes::BytesMut::new();
+
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) if !body.is_empty() && !e.is_timeout() => {
+                tracing::warn!(
+                    "Body of {} ended prematurely after {} bytes: {}",
+                    response.url(),
+                    body.len(),
+                    e
+                );
+                return Ok((body.freeze(), true));
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let remaining = max_bytes - body.len();
+
+        if chunk.len() > remaining {
+            body.extend_from_slice(&chunk[..remaining]);
+            return Ok((body.freeze(), true));
+        }
+
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok((body.freeze(), false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Echoes the `Accept` header of the request, as plain text.
+    async fn echo_accept(headers: axum::http::HeaderMap) -> impl axum::response::IntoResponse {
+        let accept = headers
+            .get(reqwest::header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+
+        ([(reqwest::header::CONTENT_TYPE, "text/plain")], accept)
+    }
+
+    fn serving(content_type: &'static str, body: &'static str) -> axum::Router {
+        axum::Router::new().route(
+            "/",
+            axum::routing::get(move || async move {
+                ([(reqwest::header::CONTENT_TYPE, content_type)], body)
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn forwards_accept() {
+        let base = crate::test_support::serve(
+            axum::Router::new().route("/", axum::routing::get(echo_accept)),
+        )
+        .await;
+
+        let options = FetchOptions {
+            accept: Some("application/json".to_owned()),
+            ..Default::default()
+        };
+
+        let page = fetch_with_reqwest(&http_client().unwrap(), &base, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(page.content, "application/json");
+    }
+
+    #[tokio::test]
+    async fn converts_by_content_type() {
+        let client = http_client().unwrap();
+        let options = FetchOptions::default();
+
+        let json = crate::test_support::serve(serving("application/json", r#"{"a":[1]}"#)).await;
+        let page = fetch_with_reqwest(&client, &json, &options).await.unwrap();
+        assert_eq!(page.content, "{\n  \"a\": [\n    1\n  ]\n}");
+
+        let html = crate::test_support::serve(serving(
+            "text/html; charset=utf-8",
+            "<html><body><h1>Title</h1></body></html>",
+        ))
+        .await;
+        let page = fetch_with_reqwest(&client, &html, &options).await.unwrap();
+        assert_eq!(page.content.trim(), "# Title");
+
+        let csv = crate::test_support::serve(serving("text/csv", "a,b\n1,2\n")).await;
+        let page = fetch_with_reqwest(&client, &csv, &options).await.unwrap();
+        assert_eq!(page.content, "a,b\n1,2\n");
+    }
+
+    #[tokio::test]
+    async fn bounds_retries_by_the_timeout() {
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                "too late"
+            }),
+        ))
+        .await;
+
+        let options = FetchOptions {
+            timeout: Some(Duration::from_millis(300)),
+            ..Default::default()
+        };
+
+        let started = std::time::Instant::now();
+        let error = fetch_with_reqwest(&http_client().unwrap(), &base, &options)
+            .await
+            .err()
+            .unwrap();
+
+        // Retrying each timed-out attempt would take at least three times as long
+        assert!(started.elapsed() < Duration::from_millis(600));
+        assert!(error.to_string().contains("Timed out"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn rejects_disallowed_content_type() {
+        let base = crate::test_support::serve(serving("image/png", "\u{89}PNG")).await;
+
+        let error = fetch_with_reqwest(&http_client().unwrap(), &base, &FetchOptions::default())
+            .await
+            .err()
+            .unwrap();
+
+        assert!(error.is::<DisallowedContentType>());
+    }
+
+    #[tokio::test]
+    async fn never_converts_pdf() {
+        let base = crate::test_support::serve(serving("application/pdf", "%PDF-1.7")).await;
+
+        let error = fetch_with_reqwest(&http_client().unwrap(), &base, &FetchOptions::default())
+            .await
+            .err()
+            .unwrap();
+
+        // PDF is allowed by default, and reported as a download
+        let attachment = error.downcast_ref::<Attachment>().unwrap();
+        assert_eq!(
+            attachment.0.content_type.as_deref(),
+            Some("application/pdf")
+        );
+    }
+
+    #[test]
+    fn matches_content_type_patterns() {
+        let allowed = ["text/*".to_owned(), "application/json".to_owned()];
+
+        assert!(is_allowed_content_type("text/plain", &allowed));
+        assert!(is_allowed_content_type("application/json", &allowed));
+        assert!(!is_allowed_content_type("image/png", &allowed));
+        // A missing type is treated as HTML
+        assert!(is_allowed_content_type("", &allowed));
+        assert!(!is_allowed_content_type(
+            "",
+            &["application/json".to_owned()]
+        ));
+    }
+
+    #[tokio::test]
+    async fn stops_reading_at_max_bytes() {
+        let body = "x".repeat(100_000);
+        let base = crate::test_support::serve(
+            axum::Router::new().route("/", axum::routing::get(move || async move { body })),
+        )
+        .await;
+
+        let response = get(&http_client().unwrap(), &base).send().await.unwrap();
+        let (bytes, truncated) = read_body(response, 1000).await.unwrap();
+
+        assert_eq!(bytes.len(), 1000);
+        assert!(truncated);
+    }
+
+    /// Serves `chunks` chunks of 1000 bytes, without a `Content-Length`.
+    async fn chunked_server(chunks: usize) -> String {
+        crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(move || async move {
+                let stream = futures::stream::iter(
+                    (0..chunks).map(|_| Ok::<_, std::io::Error>("x".repeat(1000))),
+                );
+                (
+                    [(reqwest::header::CONTENT_TYPE, "text/plain")],
+                    axum::body::Body::from_stream(stream),
+                )
+            }),
+        ))
+        .await
+    }
+
+    #[tokio::test]
+    async fn counts_the_bytes_of_bodies_without_length() {
+        let base = chunked_server(10).await;
+
+        let response = get(&http_client().unwrap(), &base).send().await.unwrap();
+        assert_eq!(response.content_length(), None);
+
+        let (bytes, truncated) = read_body(response, 2500).await.unwrap();
+        assert_eq!(bytes.len(), 2500);
+        assert!(truncated);
+
+        let max_bytes = crate::config::config().fetch_max_bytes;
+        let base = chunked_server(max_bytes / 1000 + 2).await;
+
+        let page = fetch_with_reqwest(&http_client().unwrap(), &base, &FetchOptions::default())
+            .await
+            .unwrap();
+        let truncation = page.truncation.unwrap();
+
+        assert_eq!(page.content.len(), max_bytes);
+        assert_eq!(truncation.returned_length, max_bytes);
+        // The full length is unknown without `Content-Length`
+        assert_eq!(truncation.original_length, None);
+    }
+
+    #[tokio::test]
+    async fn reads_short_bodies_in_full() {
+        let base = crate::test_support::serve(serving("text/plain", "short")).await;
+
+        let response = get(&http_client().unwrap(), &base).send().await.unwrap();
+        let (bytes, truncated) = read_body(response, 1000).await.unwrap();
+
+        assert_eq!(&bytes[..], b"short");
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        // A date in the past means retrying right away
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("-1"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+
+        let later =
+            httpdate::fmt_http_date(std::time::SystemTime::now() + Duration::from_secs(3600));
+        let delay = parse_retry_after(&later).unwrap();
+        assert!(delay > Duration::from_secs(3590) && delay <= Duration::from_secs(3600));
+    }
+
+    #[tokio::test]
+    async fn reports_retry_after_of_rate_limited_pages() {
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(|| async {
+                (
+                    reqwest::StatusCode::TOO_MANY_REQUESTS,
+                    [(reqwest::header::RETRY_AFTER, "3600")],
+                )
+            }),
+        ))
+        .await;
+
+        let page = fetch_with_reqwest(&http_client().unwrap(), &base, &FetchOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(page.status, reqwest::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(page.retry_after, Some(Duration::from_secs(3600)));
+    }
+
+    #[tokio::test]
+    async fn validates_every_redirect() {
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(|| async { axum::response::Redirect::to("http://[::1]:8081/") }),
+        ))
+        .await;
+
+        let error = fetch_html(&http_client().unwrap(), &base)
+            .await
+            .unwrap_err();
+
+        assert!(crate::ssrf::Blocked::find(error.as_ref()).is_some());
+    }
+
+    #[tokio::test]
+    async fn reports_the_status_of_error_pages() {
+        let error_page = |status| {
+            move || async move {
+                (
+                    status,
+                    axum::response::Html("<h1>Error page</h1>".repeat(50)),
+                )
+            }
+        };
+        let base = crate::test_support::serve(
+            axum::Router::new()
+                .route("/ok", axum::routing::get(|| async { "found" }))
+                .route(
+                    "/missing",
+                    axum::routing::get(error_page(reqwest::StatusCode::NOT_FOUND)),
+                )
+                .route(
+                    "/forbidden",
+                    axum::routing::get(error_page(reqwest::StatusCode::FORBIDDEN)),
+                ),
+        )
+        .await;
+
+        let client = http_client().unwrap();
+        let fetch = |path: &str| {
+            let url = format!("{}{}", base, path);
+            let client = client.clone();
+            async move {
+                fetch_with_reqwest(&client, &url, &FetchOptions::default())
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let ok = fetch("/ok").await;
+        assert_eq!(ok.status, reqwest::StatusCode::OK);
+        assert_eq!(ok.content, "found");
+
+        // Error pages are never passed off as content, however long
+        for (path, status) in [
+            ("/missing", reqwest::StatusCode::NOT_FOUND),
+            ("/forbidden", reqwest::StatusCode::FORBIDDEN),
+        ] {
+            let page = fetch(path).await;
+            assert_eq!(page.status, status);
+            assert!(page.content.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_failed_tls_handshakes() {
+        // A plain HTTP server can't complete a TLS handshake
+        let base = crate::test_support::serve(serving("text/plain", "plain")).await;
+        let url = base.replacen("http://", "https://", 1);
+
+        let error = fetch_with_reqwest(&http_client().unwrap(), &url, &FetchOptions::default())
+            .await
+            .err()
+            .unwrap();
+
+        let error = error.downcast_ref::<reqwest::Error>().unwrap();
+        assert!(error.is_connect(), "{:?}", error);
+        assert!(crate::retry::Retryable::is_retryable(error));
+
+        let results = crate::tool::fetch::fetch(crate::tool::fetch::Input {
+            urls: vec![url.clone()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(results[0].status, crate::tool::fetch::FetchStatus::Error);
+        assert!(
+            results[0]
+                .content
+                .starts_with(&format!("Error fetching {}", url)),
+            "{}",
+            results[0].content
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_noscript_content() {
+        let base = crate::test_support::serve(serving(
+            "text/html",
+            r#"<html><body>
+                <div id="app"></div>
+                <script>render()</script>
+                <noscript><h1>Pricing</h1><p>The basic plan costs ten dollars per month, billed yearly.</p></noscript>
+            </body></html>"#,
+        ))
+        .await;
+
+        let options = FetchOptions {
+            min_content_length: 50,
+            ..Default::default()
+        };
+
+        let page = fetch_with_reqwest(&http_client().unwrap(), &base, &options)
+            .await
+            .unwrap();
+
+        assert!(page.content.contains("# Pricing"), "{}", page.content);
+        assert!(
+            page.content
+                .contains("The basic plan costs ten dollars per month, billed yearly."),
+            "{}",
+            page.content
+        );
+    }
+}
@@ -0,0 +1,442 @@
+//! Preserves math as LaTeX through the markdown conversion.
+//!
+//! MathML, KaTeX and MathJax markup is flattened to garbled text by
+//! html2md, so equations are swapped for placeholders before the
+//! conversion and restored as `$...$` / `$$...$$` afterwards.
+
+use scraper::{ElementRef, Html, Node};
+
+/// Elements whose text must not be escaped when serialized.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style"];
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Classes of MathJax v2 output, which is rendered glyphs next to the
+/// `<script type="math/tex">` holding the source.
+const MATHJAX_RENDERED_CLASSES: &[&str] = &[
+    "MathJax",
+    "MathJax_Display",
+    "MathJax_Preview",
+    "MathJax_CHTML",
+    "MathJax_SVG",
+    "MathJax_SVG_Display",
+];
+
+/// Replaces every equation of `html` with a placeholder, returning the
+/// rewritten HTML and the LaTeX of each placeholder in order. Returns `None`
+/// when the page has no math.
+pub(super) fn extract_math(html: &str) -> Option<(String, Vec<String>)> {
+    let lower = html.to_ascii_lowercase();
+    let has_math = ["<math", "katex", "math/tex", "<mjx-container"]
+        .iter()
+        .any(|marker| lower.contains(marker));
+
+    if !has_math {
+        return None;
+    }
+
+    let document = Html::parse_document(html);
+
+    let mut rewritten = String::with_capacity(html.len());
+    let mut equations = vec![];
+
+    for child in document.tree.root().children() {
+        serialize(child, &mut rewritten, &mut equations);
+    }
+
+    (!equations.is_empty()).then_some((rewritten, equations))
+}
+
+/// Puts the equations back in place of their placeholders.
+pub(super) fn restore_math(markdown: &str, equations: &[String]) -> String {
+    let mut markdown = markdown.to_owned();
+
+    for (index, latex) in equations.iter().enumerate() {
+        markdown = markdown.replacen(&placeholder(index), latex, 1);
+    }
+
+    markdown
+}
+
+fn placeholder(index: usize) -> String {
+    // Alphanumeric so that html2md leaves it untouched
+    format!("XMATHPLACEHOLDER{}X", index)
+}
+
+enum Math {
+    Equation { latex: String, display: bool },
+    Rendering,
+}
+
+/// Recognizes the root element of an equation.
+fn recognize(element: ElementRef) -> Option<Math> {
+    let value = element.value();
+    let name = value.name();
+
+    if name == "script" {
+        let kind = value.attr("type")?.to_ascii_lowercase();
+        return kind.starts_with("math/tex").then(|| Math::Equation {
+            latex: element.text().collect::<String>().trim().to_owned(),
+            display: kind.contains("mode=display"),
+        });
+    }
+
+    if value
+        .classes()
+        .any(|class| MATHJAX_RENDERED_CLASSES.contains(&class))
+    {
+        return Some(Math::Rendering);
+    }
+
+    let katex_display = value.has_class("katex-display", scraper::CaseSensitivity::CaseSensitive);
+    let katex = value.has_class("katex", scraper::CaseSensitivity::CaseSensitive);
+
+    if katex_display || katex || name == "mjx-container" {
+        let display = katex_display || value.attr("display") == Some("true");
+        return Some(
+            find_math(element)
+                .map(|math| Math::Equation {
+                    latex: math_to_latex(math),
+                    display,
+                })
+                .unwrap_or(Math::Rendering),
+        );
+    }
+
+    (name == "math").then(|| Math::Equation {
+        latex: math_to_latex(element),
+        display: value.attr("display") == Some("block"),
+    })
+}
+
+fn find_math(element: ElementRef) -> Option<ElementRef> {
+    element
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .find(|e| e.value().name() == "math")
+}
+
+/// Serializes a node back to HTML, with equations swapped for placeholders.
+fn serialize(node: ego_tree::NodeRef<Node>, out: &mut String, equations: &mut Vec<String>) {
+    match node.value() {
+        Node::Text(text) => {
+            let raw = node
+                .parent()
+                .and_then(|parent| parent.value().as_element())
+                .is_some_and(|parent| RAW_TEXT_ELEMENTS.contains(&parent.name()));
+
+            if raw {
+                out.push_str(text);
+            } else {
+                push_escaped(out, text, false);
+            }
+        }
+        Node::Element(element) => {
+            if let Some(math) = ElementRef::wrap(node).and_then(recognize) {
+                if let Math::Equation { latex, display } = math
+                    && !latex.is_empty()
+                {
+                    let latex = if display {
+                        format!("$${}$$", latex)
+                    } else {
+                        format!("${}$", latex)
+                    };
+                    // Inline math stays bare text, keeping the surrounding spaces
+                    if display {
+                        out.push_str(&format!("<p>{}</p>", placeholder(equations.len())));
+                    } else {
+                        out.push_str(&placeholder(equations.len()));
+                    }
+                    equations.push(latex);
+                }
+                return;
+            }
+
+            out.push('<');
+            out.push_str(element.name());
+            for (name, value) in element.attrs() {
+                out.push(' ');
+                out.push_str(name);
+                out.push_str("=\"");
+                push_escaped(out, value, true);
+                out.push('"');
+            }
+            out.push('>');
+
+            if VOID_ELEMENTS.contains(&element.name()) {
+                return;
+            }
+
+            for child in node.children() {
+                serialize(child, out, equations);
+            }
+
+            out.push_str("</");
+            out.push_str(element.name());
+            out.push('>');
+        }
+        Node::Document | Node::Fragment => {
+            for child in node.children() {
+                serialize(child, out, equations);
+            }
+        }
+        Node::Doctype(_) | Node::Comment(_) | Node::ProcessingInstruction(_) => {}
+    }
+}
+
+fn push_escaped(out: &mut String, text: &str, attribute: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' if !attribute => out.push_str("&lt;"),
+            '>' if !attribute => out.push_str("&gt;"),
+            '"' if attribute => out.push_str("&quot;"),
+            '\u{a0}' => out.push_str("&nbsp;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Converts a `<math>` element to LaTeX, preferring the TeX annotation left
+/// by the authoring tool (as KaTeX and most converters do).
+fn math_to_latex(math: ElementRef) -> String {
+    let annotation = math.descendants().filter_map(ElementRef::wrap).find(|e| {
+        e.value().name() == "annotation"
+            && e.value()
+                .attr("encoding")
+                .is_some_and(|encoding| encoding.eq_ignore_ascii_case("application/x-tex"))
+    });
+
+    match annotation {
+        Some(annotation) => annotation.text().collect::<String>().trim().to_owned(),
+        None => convert_children(math).trim().to_owned(),
+    }
+}
+
+fn convert_children(element: ElementRef) -> String {
+    let mut latex = String::new();
+
+    for child in element.children().filter_map(ElementRef::wrap) {
+        let converted = convert(child);
+
+        // Keep commands such as `\alpha` from running into the next letter
+        if latex.ends_with(|c: char| c.is_ascii_alphabetic())
+            && converted.starts_with(|c: char| c.is_ascii_alphabetic())
+            && command_at_end(&latex)
+        {
+            latex.push(' ');
+        }
+
+        latex.push_str(&converted);
+    }
+
+    latex
+}
+
+fn command_at_end(latex: &str) -> bool {
+    let word = latex.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    word.ends_with('\\')
+}
+
+/// Converts a presentation MathML element to LaTeX.
+fn convert(element: ElementRef) -> String {
+    let children: Vec<ElementRef> = element.children().filter_map(ElementRef::wrap).collect();
+    let arg = |index: usize| {
+        children
+            .get(index)
+            .map(|&child| convert(child))
+            .unwrap_or_default()
+    };
+    let text = || element.text().collect::<String>().trim().to_owned();
+
+    match element.value().name() {
+        "mi" | "mn" | "mo" => text().chars().map(symbol).collect(),
+        "mtext" => format!("\\text{{{}}}", text()),
+        "mspace" => " ".to_owned(),
+        "mfrac" => format!("\\frac{{{}}}{{{}}}", arg(0), arg(1)),
+        "msqrt" => format!("\\sqrt{{{}}}", convert_children(element)),
+        "mroot" => format!("\\sqrt[{}]{{{}}}", arg(1), arg(0)),
+        "msup" => format!("{}^{{{}}}", group(&arg(0)), arg(1)),
+        "msub" => format!("{}_{{{}}}", group(&arg(0)), arg(1)),
+        "msubsup" | "munderover" => {
+            format!("{}_{{{}}}^{{{}}}", group(&arg(0)), arg(1), arg(2))
+        }
+        "munder" => format!("\\underset{{{}}}{{{}}}", arg(1), arg(0)),
+        "mover" => format!("\\overset{{{}}}{{{}}}", arg(1), arg(0)),
+        "mfenced" => {
+            let value = element.value();
+            let separator = value
+                .attr("separators")
+                .and_then(|separators| separators.trim().chars().next())
+                .unwrap_or(',');
+            format!(
+                "\\left{}{}\\right{}",
+                value.attr("open").unwrap_or("("),
+                children
+                    .iter()
+                    .map(|&child| convert(child))
+                    .collect::<Vec<_>>()
+                    .join(&separator.to_string()),
+                value.attr("close").unwrap_or(")"),
+            )
+        }
+        "mtable" => format!(
+            "\\begin{{matrix}}{}\\end{{matrix}}",
+            children
+                .iter()
+                .map(|&row| {
+                    row.children()
+                        .filter_map(ElementRef::wrap)
+                        .map(convert_children)
+                        .collect::<Vec<_>>()
+                        .join(" & ")
+                })
+                .collect::<Vec<_>>()
+                .join(" \\\\ ")
+        ),
+        // The first child of `<semantics>` is the presentation markup
+        "semantics" => arg(0),
+        "annotation" | "annotation-xml" => String::new(),
+        _ => convert_children(element),
+    }
+}
+
+/// Wraps a base in braces unless it is a single token.
+fn group(latex: &str) -> String {
+    if latex.chars().count() == 1 || (latex.starts_with('\\') && !latex[1..].contains('\\')) {
+        latex.to_owned()
+    } else {
+        format!("{{{}}}", latex)
+    }
+}
+
+/// Maps a Unicode symbol to its LaTeX command.
+fn symbol(c: char) -> String {
+    let command = match c {
+        'α' => "\\alpha",
+        'β' => "\\beta",
+        'γ' => "\\gamma",
+        'δ' => "\\delta",
+        'ε' => "\\epsilon",
+        'θ' => "\\theta",
+        'λ' => "\\lambda",
+        'μ' => "\\mu",
+        'π' => "\\pi",
+        'ρ' => "\\rho",
+        'σ' => "\\sigma",
+        'τ' => "\\tau",
+        'φ' => "\\phi",
+        'ω' => "\\omega",
+        'Γ' => "\\Gamma",
+        'Δ' => "\\Delta",
+        'Σ' => "\\Sigma",
+        'Ω' => "\\Omega",
+        '∑' => "\\sum",
+        '∏' => "\\prod",
+        '∫' => "\\int",
+        '∂' => "\\partial",
+        '∇' => "\\nabla",
+        '∞' => "\\infty",
+        '±' => "\\pm",
+        '×' => "\\times",
+        '·' | '⋅' => "\\cdot",
+        '÷' => "\\div",
+        '≤' => "\\leq",
+        '≥' => "\\geq",
+        '≠' => "\\neq",
+        '≈' => "\\approx",
+        '≡' => "\\equiv",
+        '∈' => "\\in",
+        '∉' => "\\notin",
+        '⊂' => "\\subset",
+        '∪' => "\\cup",
+        '∩' => "\\cap",
+        '→' => "\\to",
+        '⇒' => "\\Rightarrow",
+        '∀' => "\\forall",
+        '∃' => "\\exists",
+        '−' => "-",
+        '\u{2061}' | '\u{2062}' | '\u{2063}' => "",
+        c => return c.to_string(),
+    };
+
+    command.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert_page(html: &str) -> String {
+        super::super::convert::process_html(html)
+    }
+
+    #[test]
+    fn leaves_pages_without_math_unchanged() {
+        let html = "<html><body><p>No equations, only 1 < 2 &amp; prose.</p></body></html>";
+
+        assert!(extract_math(html).is_none());
+        assert_eq!(convert_page(html), html2md::rewrite_html(html, false));
+    }
+
+    #[test]
+    fn prefers_katex_annotations() {
+        let html = r#"<p>Energy: <span class="katex"><span class="katex-mathml"><math><semantics><mrow><mi>E</mi><mo>=</mo><mi>m</mi><msup><mi>c</mi><mn>2</mn></msup></mrow><annotation encoding="application/x-tex">E = mc^2</annotation></semantics></math></span><span class="katex-html" aria-hidden="true">E=mc2</span></span>.</p>"#;
+
+        let (_, equations) = extract_math(html).unwrap();
+        assert_eq!(equations, ["$E = mc^2$"]);
+
+        let markdown = convert_page(html);
+        assert_eq!(markdown.trim(), "Energy: $E = mc^2$.");
+    }
+
+    #[test]
+    fn reads_mathjax_scripts() {
+        let html = r#"<p>Inline <span class="MathJax">x²</span><script type="math/tex">x^2</script> and</p>
+            <div class="MathJax_Display">∑</div><script type="math/tex; mode=display">\sum_{i=1}^n i</script>"#;
+
+        let (_, equations) = extract_math(html).unwrap();
+        assert_eq!(equations, ["$x^2$", "$$\\sum_{i=1}^n i$$"]);
+
+        let markdown = convert_page(html);
+        assert!(markdown.contains("Inline $x^2$ and"), "{markdown}");
+        assert!(markdown.contains("$$\\sum_{i=1}^n i$$"), "{markdown}");
+        // The rendered glyphs are dropped
+        assert!(!markdown.contains('²'), "{markdown}");
+    }
+
+    #[test]
+    fn converts_mathml_to_latex() {
+        let html = r#"<p><math><mfrac><mrow><mi>a</mi><mo>+</mo><mn>1</mn></mrow><mi>b</mi></mfrac></math>,
+            <math><msup><mi>e</mi><mrow><mi>i</mi><mi>π</mi></mrow></msup></math>,
+            <math><msubsup><mo>∫</mo><mn>0</mn><mi>∞</mi></msubsup></math></p>"#;
+
+        let (_, equations) = extract_math(html).unwrap();
+
+        assert_eq!(
+            equations,
+            ["$\\frac{a+1}{b}$", "$e^{i\\pi}$", "$\\int_{0}^{\\infty}$"]
+        );
+    }
+
+    #[test]
+    fn places_inline_and_display_math() {
+        let html =
+            r#"<p>Since <math><mi>x</mi></math> holds:</p><math display="block"><mi>y</mi></math>"#;
+
+        let (rewritten, equations) = extract_math(html).unwrap();
+
+        assert_eq!(equations, ["$x$", "$$y$$"]);
+        // Inline math stays in its sentence, display math gets a paragraph
+        assert!(rewritten.contains("<p>Since XMATHPLACEHOLDER0X holds:</p>"));
+        assert!(rewritten.contains("<p>XMATHPLACEHOLDER1X</p>"));
+
+        let markdown = convert_page(html);
+        let lines = markdown.lines().map(str::trim).collect::<Vec<_>>();
+        assert!(lines.contains(&"Since $x$ holds:"), "{markdown:?}");
+        assert!(lines.contains(&"$$y$$"), "{markdown:?}");
+    }
+}
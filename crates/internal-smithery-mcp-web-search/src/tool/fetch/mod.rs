@@ -0,0 +1,2326 @@
+mod browser;
+mod convert;
+mod http;
+mod math;
+mod readability;
+
+use std::{str::FromStr, sync::LazyLock, time::Duration};
+
+use futures::StreamExt;
+use rmcp::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::Instrument;
+
+use crate::cache::{CachePolicy, TtlCache};
+
+/// Minimum number of characters a fetched page must contain before it is
+/// considered sufficient, unless `FETCH_MIN_CONTENT_LENGTH` says otherwise.
+/// Anything shorter falls back to the next strategy.
+pub(crate) const MIN_CONTENT_LENGTH: usize = 300;
+
+/// Bounds of `timeout_secs`: a page can't hang a call indefinitely, nor be
+/// given no time at all.
+const MIN_TIMEOUT_SECS: u64 = 1;
+const MAX_TIMEOUT_SECS: u64 = 120;
+
+pub(crate) type Error = Box<dyn std::error::Error + Send + Sync>;
+
+pub(crate) use browser::{check_navigated_url, new_tab};
+pub(crate) use convert::{markdown_to_text, offload, process_html};
+pub(crate) use http::{fetch_html, get, head, http_client};
+pub use readability::Readability;
+
+/// Successfully fetched pages are keyed by URL and every option changing
+/// their content.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    url: String,
+    accept: Option<String>,
+    min_content_length: usize,
+    auto_accept_cookies: bool,
+    frame_name: Option<String>,
+    selector: Option<String>,
+    prefer_mobile_site: bool,
+}
+
+static CACHE: LazyLock<TtlCache<CacheKey, FetchResult>> = LazyLock::new(TtlCache::new);
+
+/// Bounds the calls fetching pages at once, whichever tool makes them, as
+/// each may hold connections, memory and browser tabs.
+static FETCH_PERMITS: LazyLock<tokio::sync::Semaphore> =
+    LazyLock::new(|| tokio::sync::Semaphore::new(crate::config::config().fetch_max_concurrency));
+
+/// Waits for one of the `FETCH_MAX_CONCURRENCY` slots. Taken once per tool
+/// call, by the function requesting its pages, so that it's never nested.
+pub(crate) async fn fetch_permit() -> Result<tokio::sync::SemaphorePermit<'static>, Error> {
+    Ok(FETCH_PERMITS.acquire().await?)
+}
+
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct Input {
+    /// A list of URLs to fetch.
+    pub urls: Vec<String>,
+
+    /// The `Accept` header to send with the request.
+    /// e.g., `application/json` to request the JSON representation
+    /// from an endpoint that serves both HTML and JSON.
+    pub accept: Option<String>,
+
+    /// The order of the results: `input` (default), `relevance` (same as `input`),
+    /// `alphabetical` (by URL) or `shortest_first` (by content length).
+    pub order: Option<crate::order::Order>,
+
+    /// If `true`, each result also carries a plain-text version of the content
+    /// (without markdown syntax), e.g. for embedding or full-text search.
+    pub dual_output: Option<bool>,
+
+    /// The minimum number of characters a page fetched without the browser must
+    /// contain to be accepted (default: `FETCH_MIN_CONTENT_LENGTH`, or 300). Shorter pages are re-fetched with
+    /// the browser. Use `0` to accept short pages as-is, e.g. a glossary entry,
+    /// or a higher value for single-page apps whose shell passes the default.
+    #[serde(alias = "min_length")]
+    pub min_content_length: Option<usize>,
+
+    /// If `true`, detects the language of each page and includes its
+    /// ISO 639-3 code (e.g. `eng`, `jpn`) and the detection confidence.
+    pub detect_language: Option<bool>,
+
+    /// If `true`, includes readability metrics of each page's text:
+    /// Flesch reading ease, Flesch-Kincaid grade, ARI and Coleman-Liau index.
+    pub readability: Option<bool>,
+
+    /// If specified, the content of each page is split into chunks of at most
+    /// this many characters, cut at paragraph and heading boundaries
+    /// (e.g. for embedding). The chunks replace `content`.
+    pub chunk_size: Option<usize>,
+
+    /// `separate` (default) returns each page as its own content item, as JSON
+    /// with its metadata. `concatenated` returns a single text block with the
+    /// content of every page, each under a header with its URL.
+    pub result_format: Option<ResultFormat>,
+
+    /// If `false`, results are returned in the order they complete, each with
+    /// the `index` of its URL in `urls`, so that the fastest pages can be
+    /// processed first (default: `true`). An explicit `order` still applies.
+    pub ordered: Option<bool>,
+
+    /// If specified, only the section under the heading matching this name is
+    /// returned, up to the next heading of the same or a higher level.
+    /// Matching is case-insensitive and tolerant, e.g. `install` matches
+    /// `## Installation`.
+    pub section: Option<String>,
+
+    /// If specified, the timeout in seconds of each request, and of the wait for
+    /// a page to load in the browser (defaults: 10 and 15). Raise it for slow
+    /// sites, lower it to fail fast. Clamped to 1..=120.
+    #[serde(alias = "per_url_timeout_secs")]
+    pub timeout_secs: Option<u64>,
+
+    /// If `true`, when a page is rendered with the browser, common cookie-consent
+    /// overlays are accepted before capturing the content (default: `false`).
+    pub auto_accept_cookies: Option<bool>,
+
+    /// If specified, the name or id of a `<frame>` or `<iframe>` whose content
+    /// is extracted instead of the top document. The page is then always
+    /// rendered with the browser.
+    pub frame_name: Option<String>,
+
+    /// If specified, a CSS selector, e.g. `div.api-docs`: only the first
+    /// matching element of HTML pages is converted, leaving out menus and ads.
+    /// A page where it matches nothing is reported as an error.
+    pub selector: Option<String>,
+
+    /// If `true`, pages are fetched from the `m.` host of their site when it
+    /// exists, e.g. `m.example.com` for `www.example.com`, as mobile pages are
+    /// often lighter and cleaner (default: `false`).
+    pub prefer_mobile_site: Option<bool>,
+
+    /// If specified, a JSONPath expression, e.g. `$.items[*].name`, applied to
+    /// JSON responses: only the matched values are returned, as a JSON array.
+    /// Use it to pick what you need from a large API payload.
+    pub jsonpath: Option<String>,
+}
+
+/// How the results of a fetch are laid out in the tool response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultFormat {
+    /// One content item per URL.
+    #[default]
+    Separate,
+    /// A single content item for all URLs.
+    Concatenated,
+}
+
+/// Per-request options threaded through the fetch pipeline.
+#[derive(Debug, Clone, Default)]
+struct FetchOptions {
+    accept: Option<String>,
+    min_content_length: usize,
+    auto_accept_cookies: bool,
+    frame_name: Option<String>,
+    selector: Option<String>,
+    /// Overrides the request timeout and the browser's wait for the page.
+    timeout: Option<Duration>,
+    prefer_mobile_site: bool,
+    /// Fetches from the origin even when a cached result exists.
+    bypass_cache: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchStatus {
+    Ok,
+    Error,
+    /// The batch deadline passed before this URL completed.
+    TimedOut,
+    /// The site kept answering 429 Too Many Requests.
+    RateLimited,
+    /// The URL serves a file to download (`Content-Disposition: attachment`,
+    /// or a binary type such as PDF), described by `download` instead of
+    /// being converted.
+    Download,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchResult {
+    /// The position of the URL in the input, when `ordered` is `false`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    pub url: String,
+    pub status: FetchStatus,
+    /// The extracted content, or a description of the failure.
+    pub content: String,
+    /// The HTTP status code the site answered with, when the fetch failed
+    /// because of it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    /// When the page was fetched (RFC 3339). For cached results, the time of
+    /// the original fetch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetched_at: Option<String>,
+    /// Whether the result was served from the cache.
+    pub cached: bool,
+    /// The content as plain text, when `dual_output` is requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// The detected language, when `detect_language` is requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+    /// Readability metrics of the content, when `readability` is requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub readability: Option<Readability>,
+    /// The content split into chunks, when `chunk_size` is requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<Vec<Chunk>>,
+    /// Whether the content was cut by a size limit. When it was, the lengths
+    /// (in bytes) of what was cut are given, so that the page can be re-fetched
+    /// with higher limits if needed.
+    pub truncated: bool,
+    /// The full length, when known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub returned_length: Option<usize>,
+    /// The file the URL serves, when its status is `download`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download: Option<Download>,
+}
+
+/// A file served with `Content-Disposition: attachment`, or of a binary type.
+#[derive(Debug, Clone, Serialize)]
+pub struct Download {
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    /// The size in bytes, when the server gives it.
+    pub size: Option<u64>,
+}
+
+/// How much of a page a size limit kept, in bytes.
+#[derive(Debug, Clone, Copy)]
+struct Truncation {
+    original_length: Option<usize>,
+    returned_length: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Chunk {
+    pub index: usize,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Language {
+    /// ISO 639-3 code, e.g. `eng`.
+    pub code: String,
+    /// Between 0 and 1.
+    pub confidence: f64,
+}
+
+/// Detects the language of the text, if reliable enough to be useful.
+pub(crate) fn detect_language(text: &str) -> Option<Language> {
+    let info = whatlang::detect(text)?;
+
+    Some(Language {
+        code: info.lang().code().to_owned(),
+        confidence: info.confidence(),
+    })
+}
+
+/// Joins the results into a single text block, each page under a header
+/// with its URL and, for failures, its status.
+pub fn concatenate(results: &[FetchResult]) -> String {
+    results
+        .iter()
+        .map(|result| {
+            let content = match &result.chunks {
+                Some(chunks) => chunks
+                    .iter()
+                    .map(|chunk| chunk.content.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+                None => result.content.clone(),
+            };
+
+            match result.status {
+                FetchStatus::Ok => format!("# {}\n\n{}", result.url, content),
+                status => format!("# {} ({:?})\n\n{}", result.url, status, content),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n---\n\n")
+}
+
+/// Whether nothing of a batch could be fetched. A download counts as
+/// fetched: the URL was reached, it just serves a file.
+pub fn all_failed(results: &[FetchResult]) -> bool {
+    !results.is_empty()
+        && results
+            .iter()
+            .all(|result| !matches!(result.status, FetchStatus::Ok | FetchStatus::Download))
+}
+
+impl crate::order::Ordered for FetchResult {
+    fn label(&self) -> &str {
+        &self.url
+    }
+
+    fn content_length(&self) -> usize {
+        self.content.chars().count()
+    }
+}
+
+impl FetchResult {
+    fn new(url: &str, status: FetchStatus, content: String) -> Self {
+        Self {
+            index: None,
+            url: url.to_owned(),
+            status,
+            content,
+            http_status: None,
+            fetched_at: None,
+            cached: false,
+            text: None,
+            language: None,
+            readability: None,
+            chunks: None,
+            truncated: false,
+            original_length: None,
+            returned_length: None,
+            download: None,
+        }
+    }
+
+    fn with_truncation(mut self, truncation: Option<Truncation>) -> Self {
+        if let Some(truncation) = truncation {
+            self.truncated = true;
+            self.original_length = truncation.original_length;
+            self.returned_length = Some(truncation.returned_length);
+        }
+        self
+    }
+
+    fn with_http_status(mut self, status: Option<reqwest::StatusCode>) -> Self {
+        self.http_status = status.map(|status| status.as_u16());
+        self
+    }
+
+    pub(crate) fn ok(url: &str, content: String) -> Self {
+        Self::new(url, FetchStatus::Ok, content)
+    }
+
+    pub(crate) fn error(url: &str, message: String) -> Self {
+        Self::new(url, FetchStatus::Error, message)
+    }
+
+    fn rate_limited(url: &str, retry_after: Option<Duration>) -> Self {
+        let message = match retry_after {
+            Some(retry_after) => format!(
+                "Rate limited fetching {}: retry after {} seconds",
+                url,
+                retry_after.as_secs()
+            ),
+            None => format!("Rate limited fetching {}", url),
+        };
+
+        Self::new(url, FetchStatus::RateLimited, message)
+    }
+
+    fn download(url: &str, attachment: &http::Attachment) -> Self {
+        let mut result = Self::new(url, FetchStatus::Download, attachment.to_string());
+        result.download = Some(attachment.0.clone());
+        result
+    }
+
+    fn timed_out(url: &str) -> Self {
+        let message = format!(
+            "Timed out fetching {}: the batch deadline was exceeded",
+            url
+        );
+        Self::new(url, FetchStatus::TimedOut, message)
+    }
+}
+
+/// Paces the URLs of a `fetch` batch: plain requests run a few at a time,
+/// while browser attempts take turns on the shared browser.
+struct Pacing {
+    requests: tokio::sync::Semaphore,
+    browser: tokio::sync::Mutex<()>,
+}
+
+/// Records a page returned by fetch, by the source it came from.
+fn record_page(source: &str, content: &str) {
+    let labels = [("tool", "fetch"), ("source", source)];
+
+    crate::metrics::increment("fetch_pages_total", &labels);
+    crate::metrics::add("fetch_page_bytes_total", &labels, content.len() as f64);
+}
+
+/// Fetches a single URL within a `fetch_url` span recording the outcome.
+async fn fetch_one(
+    client: &reqwest::Client,
+    url: &str,
+    options: &FetchOptions,
+    pacing: &Pacing,
+    config: &crate::config::Config,
+) -> FetchResult {
+    let span = tracing::info_span!(
+        "fetch_url",
+        url,
+        outcome = tracing::field::Empty,
+        fallback_reason = tracing::field::Empty,
+    );
+
+    let cache_key = CacheKey {
+        url: url.to_owned(),
+        accept: options.accept.clone(),
+        min_content_length: options.min_content_length,
+        auto_accept_cookies: options.auto_accept_cookies,
+        frame_name: options.frame_name.clone(),
+        selector: options.selector.clone(),
+        prefer_mobile_site: options.prefer_mobile_site,
+    };
+
+    if !options.bypass_cache
+        && let Some(mut result) = CACHE.get(&cache_key)
+    {
+        tracing::info!("Cache hit: {}", url);
+        span.record("outcome", "cache_hit");
+        record_page("cache", &result.content);
+        result.cached = true;
+        return result;
+    }
+
+    let fetched_at = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+    let (mut result, cache_policy) = fetch_with_fallback(client, url, options, pacing, config)
+        .instrument(span.clone())
+        .await;
+
+    result.fetched_at = Some(fetched_at);
+
+    span.record("outcome", tracing::field::debug(result.status));
+
+    if result.status == FetchStatus::Ok {
+        let ttl = cache_policy.ttl(config.fetch_cache_ttl);
+        CACHE.insert(cache_key, result.clone(), ttl);
+    }
+
+    result
+}
+
+/// A source a page can be fetched from. `FETCH_FALLBACK_CHAIN` lists the
+/// strategies to try, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// A plain HTTP request.
+    Reqwest,
+    /// The latest snapshot from the Internet Archive's Wayback Machine.
+    Archive,
+    /// The headless browser, for pages rendered with JavaScript.
+    Browser,
+    /// The Jina reader proxy, which returns pages as markdown.
+    Jina,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "reqwest" => Ok(Strategy::Reqwest),
+            "archive" => Ok(Strategy::Archive),
+            "browser" => Ok(Strategy::Browser),
+            "jina" => Ok(Strategy::Jina),
+            other => Err(format!("unknown fetch strategy: {other}")),
+        }
+    }
+}
+
+impl Strategy {
+    fn name(self) -> &'static str {
+        match self {
+            Strategy::Reqwest => "reqwest",
+            Strategy::Archive => "archive",
+            Strategy::Browser => "browser",
+            Strategy::Jina => "jina",
+        }
+    }
+
+    /// The URL to request for the strategies going through reqwest.
+    fn request_url(self, url: &str) -> Option<String> {
+        match self {
+            Strategy::Reqwest => Some(url.to_owned()),
+            // `id_` returns the archived page as captured, without the Wayback toolbar
+            Strategy::Archive => Some(format!("https://web.archive.org/web/2id_/{url}")),
+            Strategy::Jina => Some(format!("https://r.jina.ai/{url}")),
+            Strategy::Browser => None,
+        }
+    }
+}
+
+/// What a single strategy of the fallback chain yielded.
+enum Attempt {
+    Sufficient(Fetched),
+    Insufficient(Fetched),
+    Failed(String),
+}
+
+/// Content a strategy managed to fetch.
+struct Fetched {
+    content: String,
+    cache_policy: CachePolicy,
+    truncation: Option<Truncation>,
+}
+
+impl Fetched {
+    fn into_result(self, url: &str) -> (FetchResult, CachePolicy) {
+        let result = FetchResult::ok(url, self.content).with_truncation(self.truncation);
+        (result, self.cache_policy)
+    }
+}
+
+impl Attempt {
+    fn classify(fetched: Fetched, options: &FetchOptions) -> Self {
+        if fetched.content.chars().count() >= options.min_content_length {
+            Attempt::Sufficient(fetched)
+        } else {
+            Attempt::Insufficient(fetched)
+        }
+    }
+
+    fn outcome(&self) -> &'static str {
+        match self {
+            Attempt::Sufficient(..) => "sufficient",
+            Attempt::Insufficient(..) => "insufficient_content",
+            Attempt::Failed(_) => "fetch_error",
+        }
+    }
+}
+
+/// Fetches a single URL with each strategy of `FETCH_FALLBACK_CHAIN` in order,
+/// until one yields sufficient content. Also returns how long the result may be cached.
+async fn fetch_with_fallback(
+    client: &reqwest::Client,
+    url: &str,
+    options: &FetchOptions,
+    pacing: &Pacing,
+    config: &crate::config::Config,
+) -> (FetchResult, CachePolicy) {
+    // Only well-formed URLs are fetched, and neither reqwest nor the browser
+    // may reach the server's own network or other schemes than http(s)
+    let target = match reqwest::Url::parse(url) {
+        Ok(target) => target,
+        Err(e) => {
+            return (
+                FetchResult::error(url, format!("Invalid URL {:?}: {}", url, e)),
+                CachePolicy::NoStore,
+            );
+        }
+    };
+
+    if let Err(blocked) = crate::ssrf::check_target(&target).await {
+        tracing::warn!("{}", blocked);
+        return (
+            FetchResult::error(url, blocked.to_string()),
+            CachePolicy::NoStore,
+        );
+    }
+
+    // The longest insufficient content, returned when no strategy does better
+    let mut best_effort: Option<Fetched> = None;
+    let mut last_error: Option<String> = None;
+    let mut http_status: Option<reqwest::StatusCode> = None;
+    let mut browser_skipped = false;
+
+    // Frames are only rendered by the browser
+    if options.frame_name.is_some()
+        && (config.disable_browser || !config.fetch_fallback_chain.contains(&Strategy::Browser))
+    {
+        return (
+            FetchResult::error(
+                url,
+                format!(
+                    "Error fetching {}: frame_name requires the browser, which is not available on this server",
+                    url
+                ),
+            ),
+            CachePolicy::NoStore,
+        );
+    }
+
+    for &strategy in &config.fetch_fallback_chain {
+        if strategy == Strategy::Browser && config.disable_browser {
+            browser_skipped = true;
+            continue;
+        }
+
+        if strategy != Strategy::Browser && options.frame_name.is_some() {
+            continue;
+        }
+
+        let span = tracing::info_span!(
+            "strategy",
+            strategy = strategy.name(),
+            status = tracing::field::Empty,
+            outcome = tracing::field::Empty,
+        );
+
+        let attempt = match strategy.request_url(url) {
+            Some(request_url) => {
+                let _permit = pacing.requests.acquire().await;
+
+                let mut result = http::fetch_with_reqwest(client, &request_url, options)
+                    .instrument(span.clone())
+                    .await;
+
+                // Honor a bounded `Retry-After` once before giving up on a rate-limited site
+                if let Ok(page) = &result
+                    && page.status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                {
+                    let wait = page.retry_after.unwrap_or(Duration::from_secs(1));
+
+                    if wait <= config.fetch_max_retry_after {
+                        tracing::info!("Rate limited, retrying in {:?}: {}", wait, url);
+                        tokio::time::sleep(wait).await;
+
+                        result = http::fetch_with_reqwest(client, &request_url, options)
+                            .instrument(span.clone())
+                            .await;
+                    }
+                }
+
+                match result {
+                    Ok(page) => {
+                        span.record("status", page.status.as_u16());
+
+                        if page.status.is_success() {
+                            Attempt::classify(
+                                Fetched {
+                                    content: page.content,
+                                    cache_policy: page.cache_policy,
+                                    truncation: page.truncation,
+                                },
+                                options,
+                            )
+                        } else if strategy == Strategy::Reqwest
+                            && page.status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                        {
+                            // Other sources would be rate-limited just the same
+                            tracing::warn!("Rate limited by {}", url);
+                            span.record("outcome", "rate_limited");
+                            return (
+                                FetchResult::rate_limited(url, page.retry_after),
+                                CachePolicy::NoStore,
+                            );
+                        } else if strategy == Strategy::Reqwest
+                            && page.status != reqwest::StatusCode::FORBIDDEN
+                        {
+                            // Error pages such as a 404 never improve with another source, so they are reported as-is
+                            tracing::warn!("Reqwest fetch returned {} for {}", page.status, url);
+                            span.record("outcome", "http_error");
+                            return (
+                                FetchResult::error(
+                                    url,
+                                    format!("Error fetching {}: HTTP {}", url, page.status),
+                                )
+                                .with_http_status(Some(page.status)),
+                                CachePolicy::NoStore,
+                            );
+                        } else {
+                            // A 403 often comes from bot protection that lets a browser through
+                            if strategy == Strategy::Reqwest {
+                                http_status = Some(page.status);
+                            }
+                            Attempt::Failed(format!("HTTP {}", page.status))
+                        }
+                    }
+                    Err(e) => {
+                        // The browser would follow the same redirect, so there is no fallback
+                        if let Some(blocked) = crate::ssrf::Blocked::find(e.as_ref()) {
+                            span.record("outcome", "blocked");
+                            return (
+                                FetchResult::error(url, blocked.to_string()),
+                                CachePolicy::NoStore,
+                            );
+                        }
+
+                        // The browser would download the file just the same
+                        if let Some(attachment) = e.downcast_ref::<http::Attachment>() {
+                            span.record("outcome", "download");
+                            return (FetchResult::download(url, attachment), CachePolicy::NoStore);
+                        }
+
+                        // Another source would serve the same resource
+                        if let Some(disallowed) = e.downcast_ref::<http::DisallowedContentType>() {
+                            span.record("outcome", "disallowed_content_type");
+                            return (
+                                FetchResult::error(
+                                    url,
+                                    format!("Error fetching {}: {}", url, disallowed),
+                                ),
+                                CachePolicy::NoStore,
+                            );
+                        }
+
+                        Attempt::Failed(e.to_string())
+                    }
+                }
+            }
+            None => {
+                let _turn = pacing.browser.lock().await;
+
+                match fetch_in_browser(url, options)
+                    .instrument(span.clone())
+                    .await
+                {
+                    Ok(content) => Attempt::classify(
+                        Fetched {
+                            content,
+                            cache_policy: CachePolicy::Default,
+                            truncation: None,
+                        },
+                        options,
+                    ),
+                    Err(e) => Attempt::Failed(e.to_string()),
+                }
+            }
+        };
+
+        span.record("outcome", attempt.outcome());
+
+        crate::metrics::increment(
+            "fetch_attempts_total",
+            &[("source", strategy.name()), ("outcome", attempt.outcome())],
+        );
+
+        if !matches!(attempt, Attempt::Sufficient(..)) {
+            tracing::Span::current().record("fallback_reason", attempt.outcome());
+        }
+
+        match attempt {
+            Attempt::Sufficient(fetched) => {
+                record_page(strategy.name(), &fetched.content);
+                return fetched.into_result(url);
+            }
+            Attempt::Insufficient(fetched) => {
+                tracing::info!(
+                    "Insufficient content from {}, falling back: {}",
+                    strategy.name(),
+                    url
+                );
+
+                if best_effort
+                    .as_ref()
+                    .is_none_or(|best| best.content.len() < fetched.content.len())
+                {
+                    best_effort = Some(fetched);
+                }
+            }
+            Attempt::Failed(e) => {
+                tracing::warn!("{} fetch failed for {}: {}", strategy.name(), url, e);
+                last_error = Some(format!("{}: {}", strategy.name(), e));
+            }
+        }
+    }
+
+    // Incomplete content is never cached, so that a later call may still get the full page
+    match best_effort {
+        // Best-effort: return whatever was fetched without spawning a browser
+        Some(mut fetched) if browser_skipped => {
+            fetched.content.push_str(
+                "\n\n(Note: the content may be incomplete; the browser fallback is disabled on this server.)",
+            );
+            (fetched.into_result(url).0, CachePolicy::NoStore)
+        }
+        Some(fetched) => (fetched.into_result(url).0, CachePolicy::NoStore),
+        None => (
+            FetchResult::error(
+                url,
+                format!(
+                    "Error fetching {}: {}",
+                    url,
+                    last_error.unwrap_or_else(|| "no fetch strategy is available".to_owned())
+                ),
+            )
+            .with_http_status(http_status),
+            CachePolicy::NoStore,
+        ),
+    }
+}
+
+/// The browser shared by all fetches, launched on first use. Holding the lock
+/// while launching makes concurrent first fetches wait for a single launch
+/// instead of each spawning Chrome.
+static BROWSER: tokio::sync::Mutex<Option<BrowserState>> = tokio::sync::Mutex::const_new(None);
+
+/// How long a failed launch is reported to callers before it's attempted again.
+const LAUNCH_FAILURE_COOLDOWN: Duration = Duration::from_secs(5);
+
+enum BrowserState<B = headless_chrome::Browser> {
+    Ready(B),
+    Failed(tokio::time::Instant, String),
+}
+
+async fn shared_browser() -> Result<headless_chrome::Browser, Error> {
+    get_or_launch(&BROWSER, browser::launch_browser).await
+}
+
+/// Returns the browser held by `state`, launching it with `launch` on the
+/// worker pool when there is none, or when the last launch failed longer
+/// than the cooldown ago.
+async fn get_or_launch<B, F>(
+    state: &tokio::sync::Mutex<Option<BrowserState<B>>>,
+    launch: F,
+) -> Result<B, Error>
+where
+    B: Clone + Send + 'static,
+    F: FnOnce() -> Result<B, Error> + Send + 'static,
+{
+    let mut state = state.lock().await;
+
+    match &*state {
+        Some(BrowserState::Ready(browser)) => return Ok(browser.clone()),
+        Some(BrowserState::Failed(at, e)) if at.elapsed() < LAUNCH_FAILURE_COOLDOWN => {
+            return Err(format!("launching browser: {e}").into());
+        }
+        _ => {}
+    }
+
+    match crate::worker::spawn(launch).await? {
+        Ok(browser) => {
+            *state = Some(BrowserState::Ready(browser.clone()));
+            Ok(browser)
+        }
+        Err(e) => {
+            *state = Some(BrowserState::Failed(
+                tokio::time::Instant::now(),
+                e.to_string(),
+            ));
+            Err(format!("launching browser: {e}").into())
+        }
+    }
+}
+
+/// Forgets the shared browser, so that the next fetch launches a new one.
+async fn reset_browser() {
+    *BROWSER.lock().await = None;
+}
+
+/// Closes the tabs of the shared browser and lets its process exit, on
+/// shutdown, so that no Chrome process outlives the server.
+pub(crate) async fn shutdown_browser() {
+    let Some(BrowserState::Ready(browser)) = BROWSER.lock().await.take() else {
+        return;
+    };
+
+    tracing::info!("Closing the browser");
+
+    let closing = crate::worker::spawn(move || {
+        let tabs = browser
+            .get_tabs()
+            .lock()
+            .map(|tabs| tabs.clone())
+            .unwrap_or_default();
+
+        for tab in tabs {
+            let _ = tab.close(false);
+        }
+
+        // Dropping the last handle kills the process
+        drop(browser);
+    });
+
+    if let Err(e) = closing.await {
+        tracing::warn!("Failed to close the browser: {}", e);
+    }
+}
+
+/// Renders a page with the shared browser, returning its final URL and HTML,
+/// for the tools extracting structure from pages built with JavaScript.
+pub(crate) async fn fetch_rendered_html(url: &str) -> Result<(reqwest::Url, String), Error> {
+    if crate::config::config().disable_browser {
+        return Err("the browser is disabled on this server".into());
+    }
+
+    crate::ssrf::check_target(&reqwest::Url::parse(url)?).await?;
+
+    let _permit = fetch_permit().await?;
+
+    tracing::info!("Rendering HTML: {}", url);
+
+    let url = url.to_owned();
+    let (final_url, html) =
+        with_browser(move |browser| browser::rendered_html(browser, &url)).await?;
+
+    Ok((reqwest::Url::parse(&final_url)?, html))
+}
+
+/// Checks that the shared browser is up and answering, launching it if needed.
+pub(crate) async fn check_browser() -> Result<(), Error> {
+    with_browser(|browser| {
+        browser.get_version()?;
+        Ok(())
+    })
+    .await
+}
+
+async fn fetch_in_browser(url: &str, options: &FetchOptions) -> Result<String, Error> {
+    let url = url.to_owned();
+    let options = options.clone();
+
+    with_browser(move |browser| browser::fetch_with_browser(browser, &url, &options)).await
+}
+
+/// Runs `job` with the shared browser on the worker pool, relaunching the
+/// browser once if its connection was closed.
+pub(crate) async fn with_browser<T, F>(job: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: Fn(&headless_chrome::Browser) -> Result<T, Error> + Send + Sync + 'static,
+{
+    let job = std::sync::Arc::new(job);
+
+    match run_in_browser(std::sync::Arc::clone(&job)).await {
+        Err(e) if e.is::<browser::BrowserClosed>() => {
+            tracing::info!("Browser connection closed, relaunching");
+            reset_browser().await;
+            run_in_browser(job).await
+        }
+        result => result,
+    }
+}
+
+async fn run_in_browser<T, F>(job: std::sync::Arc<F>) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: Fn(&headless_chrome::Browser) -> Result<T, Error> + Send + Sync + 'static,
+{
+    let browser = shared_browser().await?;
+
+    let span = tracing::Span::current();
+
+    crate::worker::spawn(move || {
+        let _entered = span.enter();
+        job(&browser)
+    })
+    .await?
+}
+
+pub async fn fetch(input: Input) -> Result<Vec<FetchResult>, Error> {
+    fetch_with_cache(input, true, crate::config::config()).await
+}
+
+/// Fetches like [`fetch`], but always from the origin rather than the cache,
+/// e.g. to tell whether a page changed.
+pub(crate) async fn fetch_fresh(input: Input) -> Result<Vec<FetchResult>, Error> {
+    fetch_with_cache(input, false, crate::config::config()).await
+}
+
+async fn fetch_with_cache(
+    Input {
+        urls,
+        accept,
+        order,
+        dual_output,
+        min_content_length,
+        detect_language: should_detect_language,
+        readability: should_compute_readability,
+        chunk_size,
+        result_format: _,
+        ordered,
+        section,
+        timeout_secs,
+        auto_accept_cookies,
+        frame_name,
+        selector,
+        prefer_mobile_site,
+        jsonpath,
+    }: Input,
+    use_cache: bool,
+    config: &crate::config::Config,
+) -> Result<Vec<FetchResult>, Error> {
+    if let Some(selector) = &selector {
+        scraper::Selector::parse(selector)
+            .map_err(|e| format!("Invalid selector {:?}: {}", selector, e))?;
+    }
+
+    let jsonpath = jsonpath
+        .map(|path| {
+            serde_json_path::JsonPath::parse(&path)
+                .map_err(|e| format!("Invalid JSONPath {:?}: {}", path, e))
+        })
+        .transpose()?;
+
+    let client = http_client()?;
+
+    let _permit = fetch_permit().await?;
+
+    let options = FetchOptions {
+        accept,
+        min_content_length: min_content_length.unwrap_or(config.fetch_min_content_length),
+        auto_accept_cookies: auto_accept_cookies.unwrap_or(false),
+        frame_name,
+        selector,
+        timeout: timeout_secs
+            .map(|secs| Duration::from_secs(secs.clamp(MIN_TIMEOUT_SECS, MAX_TIMEOUT_SECS))),
+        prefer_mobile_site: prefer_mobile_site.unwrap_or(false),
+        bypass_cache: !use_cache,
+    };
+
+    let deadline = config
+        .fetch_batch_deadline
+        .map(|deadline| tokio::time::Instant::now() + deadline);
+
+    let pacing = Pacing {
+        requests: tokio::sync::Semaphore::new(config.fetch_url_concurrency),
+        browser: tokio::sync::Mutex::new(()),
+    };
+
+    // Duplicate URLs are fetched once: `positions` maps each input URL to its unique URL
+    let mut unique: Vec<&str> = vec![];
+    let mut unique_indices = std::collections::HashMap::new();
+
+    let positions: Vec<usize> = urls
+        .iter()
+        .map(|url| {
+            *unique_indices.entry(dedup_key(url)).or_insert_with(|| {
+                unique.push(url);
+                unique.len() - 1
+            })
+        })
+        .collect();
+
+    // URLs are fetched concurrently; those not completed by the deadline are marked timed out
+    let fetched: Vec<(usize, FetchResult)> = unique
+        .iter()
+        .enumerate()
+        .map(|(index, &url)| {
+            let (client, options, pacing) = (&client, &options, &pacing);
+
+            let fetching = async move {
+                let mobile = match options.prefer_mobile_site {
+                    true => mobile_url(url).await,
+                    false => None,
+                };
+
+                fetch_one(
+                    client,
+                    mobile.as_deref().unwrap_or(url),
+                    options,
+                    pacing,
+                    config,
+                )
+                .await
+            };
+
+            async move {
+                let result = match deadline {
+                    Some(deadline) => tokio::time::timeout_at(deadline, fetching).await.ok(),
+                    None => Some(fetching.await),
+                };
+
+                let result = result.unwrap_or_else(|| {
+                    tracing::warn!("Batch deadline exceeded while fetching {}", url);
+                    FetchResult::timed_out(url)
+                });
+
+                (index, result)
+            }
+        })
+        .collect::<futures::stream::FuturesUnordered<_>>()
+        .collect()
+        .await;
+
+    // Fans the results out to the positions of their URL in the input
+    let mut results = Vec::with_capacity(urls.len());
+
+    for (unique_index, result) in fetched {
+        for (index, _) in positions
+            .iter()
+            .enumerate()
+            .filter(|&(_, &position)| position == unique_index)
+        {
+            let mut result = result.clone();
+            result.url = urls[index].clone();
+            results.push((index, result));
+        }
+    }
+
+    // `results` is in completion order
+    let ordered = ordered.unwrap_or(true);
+
+    if ordered {
+        results.sort_by_key(|&(index, _)| index);
+    }
+
+    let mut results: Vec<FetchResult> = results
+        .into_iter()
+        .map(|(index, mut result)| {
+            if !ordered {
+                result.index = Some(index);
+            }
+            result
+        })
+        .collect();
+
+    if let Some(section) = section {
+        for result in results.iter_mut().filter(|r| r.status == FetchStatus::Ok) {
+            match convert::extract_section(&result.content, &section) {
+                Some(content) => result.content = content,
+                None => {
+                    let headings = convert::heading_texts(&result.content);
+
+                    *result = FetchResult::error(
+                        &result.url,
+                        format!(
+                            "No section matching \"{}\" was found. Headings on the page: {}",
+                            section,
+                            if headings.is_empty() {
+                                "(none)".to_owned()
+                            } else {
+                                headings.join(", ")
+                            }
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(jsonpath) = &jsonpath {
+        for result in results.iter_mut().filter(|r| r.status == FetchStatus::Ok) {
+            match query_json(&result.content, jsonpath) {
+                Ok(content) => result.content = content,
+                Err(e) => *result = FetchResult::error(&result.url, e),
+            }
+        }
+    }
+
+    let dual_output = dual_output.unwrap_or(false);
+    let should_detect_language = should_detect_language.unwrap_or(false);
+    let should_compute_readability = should_compute_readability.unwrap_or(false);
+
+    if dual_output || should_detect_language || should_compute_readability {
+        for result in results.iter_mut().filter(|r| r.status == FetchStatus::Ok) {
+            let text = convert::markdown_to_text(&result.content);
+
+            if should_detect_language {
+                result.language = detect_language(&text);
+            }
+
+            if should_compute_readability {
+                result.readability = readability::readability(&text);
+            }
+
+            if dual_output {
+                result.text = Some(text);
+            }
+        }
+    }
+
+    order.unwrap_or_default().apply(&mut results);
+
+    if let Some(chunk_size) = chunk_size {
+        for result in results.iter_mut().filter(|r| r.status == FetchStatus::Ok) {
+            let content = std::mem::take(&mut result.content);
+
+            let chunks = convert::chunk_markdown(&content, chunk_size)
+                .into_iter()
+                .enumerate()
+                .map(|(index, content)| Chunk { index, content })
+                .collect();
+
+            result.chunks = Some(chunks);
+        }
+    }
+
+    if let Some(max_total_bytes) = config.fetch_max_total_bytes {
+        limit_total_size(&mut results, max_total_bytes);
+    }
+
+    Ok(results)
+}
+
+/// Applies a JSONPath to the content of a JSON response, returning the
+/// matched values as a pretty-printed JSON array.
+fn query_json(content: &str, jsonpath: &serde_json_path::JsonPath) -> Result<String, String> {
+    let value = serde_json::from_str::<serde_json::Value>(content).map_err(|e| {
+        format!(
+            "The response is not valid JSON, so the JSONPath can't be applied: {}",
+            e
+        )
+    })?;
+
+    let matched: Vec<&serde_json::Value> = jsonpath.query(&value).all();
+
+    serde_json::to_string_pretty(&matched).map_err(|e| e.to_string())
+}
+
+/// Identifies duplicate URLs, ignoring the case of the scheme and host
+/// and a trailing slash.
+fn dedup_key(url: &str) -> String {
+    let url = url.trim();
+
+    match reqwest::Url::parse(url) {
+        // Parsing lowercases the scheme and host
+        Ok(parsed) => parsed.as_str().trim_end_matches('/').to_owned(),
+        Err(_) => url.trim_end_matches('/').to_owned(),
+    }
+}
+
+/// How long the `m.` host of a site may take to resolve.
+const MOBILE_LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The URL of the same page on the `m.` host of its site, if that host
+/// resolves.
+async fn mobile_url(url: &str) -> Option<String> {
+    let mobile = mobile_candidate(url)?;
+
+    let mobile_host = mobile.host_str()?;
+    let port = mobile.port_or_known_default()?;
+
+    let lookup = tokio::net::lookup_host((mobile_host, port));
+    let resolves = match tokio::time::timeout(MOBILE_LOOKUP_TIMEOUT, lookup).await {
+        Ok(Ok(mut addresses)) => addresses.next().is_some(),
+        _ => false,
+    };
+
+    if !resolves {
+        tracing::debug!(
+            "No mobile site for {}: {} doesn't resolve",
+            url,
+            mobile_host
+        );
+        return None;
+    }
+
+    tracing::info!("Fetching the mobile site: {}", mobile);
+
+    Some(mobile.to_string())
+}
+
+/// The URL of the same page on the `m.` host of its site, e.g.
+/// `m.example.com` for `www.example.com`. `None` for IP addresses and pages
+/// already on an `m.` host.
+fn mobile_candidate(url: &str) -> Option<reqwest::Url> {
+    let mut parsed = reqwest::Url::parse(url.trim()).ok()?;
+
+    let Some(url::Host::Domain(domain)) = parsed.host() else {
+        return None;
+    };
+
+    if domain.starts_with("m.") {
+        return None;
+    }
+
+    let mobile_host = format!("m.{}", domain.strip_prefix("www.").unwrap_or(domain));
+
+    parsed.set_host(Some(&mobile_host)).ok()?;
+
+    Some(parsed)
+}
+
+const CUT_NOTE: &str = "\n\n(Note: the content was cut to keep the batch within its size limit.)";
+
+const DROPPED_NOTE: &str = "(Note: the content was dropped to keep the batch within its size limit. Fetch this URL on its own to get it.)";
+
+fn serialized_len(result: &FetchResult) -> usize {
+    serde_json::to_string(result).map_or(0, |json| json.len())
+}
+
+/// The serialized length of `result` once reduced to a note.
+fn dropped_len(result: &FetchResult) -> usize {
+    let mut dropped = FetchResult {
+        content: String::new(),
+        text: None,
+        chunks: None,
+        ..result.clone()
+    };
+    drop_content(&mut dropped);
+
+    serialized_len(&dropped)
+}
+
+/// Keeps the serialized `results` within `max_total_bytes`: the result that
+/// reaches the limit is cut, and the later ones are reduced to a note.
+fn limit_total_size(results: &mut [FetchResult], max_total_bytes: usize) {
+    // The notes of the later results take room too: `notes[i]` is the length
+    // of those after the i-th result
+    let mut notes = vec![0; results.len()];
+    for index in (1..results.len()).rev() {
+        notes[index - 1] = notes[index] + dropped_len(&results[index]) + 1;
+    }
+
+    // The brackets and commas of the JSON array
+    let mut used = 2;
+
+    let Some(over) = results.iter().enumerate().position(|(index, result)| {
+        used += serialized_len(result) + 1;
+        used + notes[index] > max_total_bytes
+    }) else {
+        return;
+    };
+
+    let (kept, rest) = results.split_at_mut(over);
+    let (result, later) = rest.split_first_mut().unwrap();
+
+    for result in later.iter_mut() {
+        drop_content(result);
+    }
+
+    // Room left for the result at the limit, once the notes of the later ones are accounted for
+    let used = 2 + kept
+        .iter()
+        .chain(later.iter())
+        .map(|result| serialized_len(result) + 1)
+        .sum::<usize>();
+    let original_length = result.content.len()
+        + result
+            .chunks
+            .iter()
+            .flatten()
+            .map(|chunk| chunk.content.len())
+            .sum::<usize>();
+
+    // The fields describing the cut count towards the limit too, so they are
+    // set beforehand, with a returned length at least as long as the final one
+    result.truncated = true;
+    result.original_length.get_or_insert(original_length);
+    result.returned_length = Some(original_length + CUT_NOTE.len());
+
+    // The note is escaped in JSON
+    let note_length = serde_json::to_string(CUT_NOTE).map_or(0, |note| note.len() - 2);
+    let budget = max_total_bytes.saturating_sub(used + 1 + note_length);
+
+    if cut_to_fit(result, budget) {
+        result.content.push_str(CUT_NOTE);
+        result.returned_length = Some(result.content.len());
+    } else {
+        drop_content(result);
+    }
+}
+
+fn drop_content(result: &mut FetchResult) {
+    result.content = DROPPED_NOTE.to_owned();
+    result.text = None;
+    result.chunks = None;
+    result.language = None;
+    result.readability = None;
+    result.truncated = true;
+    result.returned_length = Some(0);
+}
+
+/// Cuts the text, chunks and content of a result until it serializes to at
+/// most `budget` bytes. Returns `false` when nothing of the content fits.
+fn cut_to_fit(result: &mut FetchResult, budget: usize) -> bool {
+    loop {
+        let len = serialized_len(result);
+
+        if len <= budget {
+            return !result.content.is_empty()
+                || result
+                    .chunks
+                    .as_ref()
+                    .is_some_and(|chunks| !chunks.is_empty());
+        }
+
+        let excess = len - budget;
+
+        // The plain text duplicates the content, so it goes first
+        if result.text.take().is_some() {
+            continue;
+        }
+
+        let content = match result.chunks.as_mut() {
+            Some(chunks) => match chunks.last_mut() {
+                Some(chunk) if chunk.content.len() > excess => &mut chunk.content,
+                Some(_) => {
+                    chunks.pop();
+                    continue;
+                }
+                None => return false,
+            },
+            None if result.content.len() > excess => &mut result.content,
+            None => return false,
+        };
+
+        let mut end = content.len() - excess;
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        content.truncate(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_each_source_of_the_chain() {
+        let url = "https://example.com/page";
+
+        assert_eq!(Strategy::Reqwest.request_url(url).as_deref(), Some(url));
+        assert_eq!(
+            Strategy::Archive.request_url(url).as_deref(),
+            Some("https://web.archive.org/web/2id_/https://example.com/page")
+        );
+        assert_eq!(
+            Strategy::Jina.request_url(url).as_deref(),
+            Some("https://r.jina.ai/https://example.com/page")
+        );
+        assert_eq!(Strategy::Browser.request_url(url), None);
+    }
+
+    #[test]
+    fn falls_back_on_insufficient_content() {
+        let options = FetchOptions {
+            min_content_length: 10,
+            ..Default::default()
+        };
+
+        let fetched = |content: &str| Fetched {
+            content: content.to_owned(),
+            cache_policy: CachePolicy::Default,
+            truncation: None,
+        };
+
+        assert!(matches!(
+            Attempt::classify(fetched("short"), &options),
+            Attempt::Insufficient(_)
+        ));
+        assert!(matches!(
+            Attempt::classify(fetched("long enough"), &options),
+            Attempt::Sufficient(_)
+        ));
+    }
+
+    /// Serves a page long enough to need no fallback, counting the requests.
+    async fn counting_server() -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let router = axum::Router::new().route(
+            "/",
+            axum::routing::get({
+                let hits = hits.clone();
+                move || async move {
+                    hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    (
+                        [(reqwest::header::CONTENT_TYPE, "text/plain")],
+                        "text ".repeat(100),
+                    )
+                }
+            }),
+        );
+
+        (crate::test_support::serve(router).await, hits)
+    }
+
+    #[tokio::test]
+    async fn caches_per_option_set() {
+        let (base, hits) = counting_server().await;
+        let hits = || hits.load(std::sync::atomic::Ordering::SeqCst);
+
+        let input = || Input {
+            urls: vec![base.clone()],
+            ..Default::default()
+        };
+
+        let first = fetch(input()).await.unwrap();
+        let second = fetch(input()).await.unwrap();
+        assert_eq!(hits(), 1);
+        assert!(!first[0].cached);
+        assert!(second[0].cached);
+
+        fetch(Input {
+            min_content_length: Some(0),
+            ..input()
+        })
+        .await
+        .unwrap();
+        assert_eq!(hits(), 2);
+
+        fetch(Input {
+            auto_accept_cookies: Some(true),
+            ..input()
+        })
+        .await
+        .unwrap();
+        assert_eq!(hits(), 3);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_fetch_time_of_cached_results() {
+        let (base, _) = counting_server().await;
+        let input = || Input {
+            urls: vec![base.clone()],
+            ..Default::default()
+        };
+
+        let first = fetch(input()).await.unwrap().remove(0);
+        // `fetched_at` has a precision of a second
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let cached = fetch(input()).await.unwrap().remove(0);
+        let fresh = fetch_fresh(input()).await.unwrap().remove(0);
+
+        assert!(cached.cached);
+        assert!(first.fetched_at.is_some());
+        assert_eq!(cached.fetched_at, first.fetched_at);
+
+        assert!(!fresh.cached);
+        assert!(fresh.fetched_at > first.fetched_at);
+    }
+
+    #[tokio::test]
+    async fn reports_long_retry_after_as_rate_limited() {
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(|| async {
+                (
+                    reqwest::StatusCode::TOO_MANY_REQUESTS,
+                    [(reqwest::header::RETRY_AFTER, "3600")],
+                )
+            }),
+        ))
+        .await;
+
+        let results = fetch(Input {
+            urls: vec![base],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(results[0].status, FetchStatus::RateLimited);
+    }
+
+    #[tokio::test]
+    async fn honors_short_retry_after() {
+        let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let router = axum::Router::new().route(
+            "/",
+            axum::routing::get({
+                let hits = hits.clone();
+                move || async move {
+                    match hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                        0 => (
+                            reqwest::StatusCode::TOO_MANY_REQUESTS,
+                            [(reqwest::header::RETRY_AFTER, "1")],
+                            "",
+                        ),
+                        _ => (
+                            reqwest::StatusCode::OK,
+                            [(reqwest::header::CONTENT_TYPE, "text/plain")],
+                            "served after waiting",
+                        ),
+                    }
+                }
+            }),
+        );
+        let base = crate::test_support::serve(router).await;
+
+        let started = std::time::Instant::now();
+        let results = fetch(Input {
+            urls: vec![base],
+            min_content_length: Some(0),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(results[0].status, FetchStatus::Ok);
+        assert_eq!(results[0].content, "served after waiting");
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_urls_per_url() {
+        let (base, hits) = counting_server().await;
+
+        let results = fetch(Input {
+            urls: vec![
+                "file:///etc/passwd".to_owned(),
+                "javascript:alert(1)".to_owned(),
+                "not a url".to_owned(),
+                base,
+            ],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let statuses = results
+            .iter()
+            .map(|result| result.status)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                FetchStatus::Error,
+                FetchStatus::Error,
+                FetchStatus::Error,
+                FetchStatus::Ok
+            ]
+        );
+
+        assert!(results[0].content.contains("file scheme is not allowed"));
+        assert!(
+            results[1]
+                .content
+                .contains("javascript scheme is not allowed")
+        );
+        assert!(results[2].content.starts_with("Invalid URL"));
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn refuses_redirects_to_internal_targets() {
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(|| async {
+                axum::response::Redirect::temporary("http://169.254.169.254/latest/meta-data/")
+            }),
+        ))
+        .await;
+
+        let results = fetch(Input {
+            urls: vec![base, "http://127.0.0.1:1/".to_owned()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        for result in results {
+            assert_eq!(result.status, FetchStatus::Error);
+            assert!(
+                result.content.contains("Blocked request"),
+                "{}",
+                result.content
+            );
+        }
+    }
+
+    /// Serves a page after `delay`, as a slow site would.
+    async fn slow_server(delay: Duration) -> String {
+        crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(move || async move {
+                tokio::time::sleep(delay).await;
+                axum::response::Html("<p>slow</p>")
+            }),
+        ))
+        .await
+    }
+
+    #[tokio::test]
+    async fn limits_concurrent_fetches() {
+        let delay = Duration::from_millis(300);
+        let base = slow_server(delay).await;
+        let client = http_client().unwrap();
+
+        let calls = crate::config::config().fetch_max_concurrency + 1;
+
+        let started = std::time::Instant::now();
+        let results =
+            futures::future::join_all((0..calls).map(|_| fetch_html(&client, &base))).await;
+
+        assert!(results.iter().all(Result::is_ok));
+        // The last call waits for a slot, then for the page
+        assert!(started.elapsed() >= delay * 2);
+    }
+
+    #[tokio::test]
+    async fn fetches_the_urls_of_a_batch_concurrently() {
+        let config = crate::config::Config {
+            fetch_url_concurrency: 4,
+            ..crate::config::config().clone()
+        };
+
+        let delay = Duration::from_millis(500);
+        let base = slow_server(delay).await;
+
+        let urls: Vec<String> = (0..4)
+            .map(|page| format!("{}/?page={}", base, page))
+            .collect();
+
+        let started = std::time::Instant::now();
+        let results = fetch_with_cache(
+            Input {
+                urls,
+                min_content_length: Some(0),
+                ..Default::default()
+            },
+            true,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert!(
+            results
+                .iter()
+                .all(|result| result.status == FetchStatus::Ok)
+        );
+        // One after the other, they would take a delay each. The margin
+        // leaves room for the tests holding every fetch permit
+        assert!(started.elapsed() < delay * 3, "{:?}", started.elapsed());
+    }
+
+    #[test]
+    fn queries_json_responses() {
+        let content = r#"{"items": [{"name": "a", "id": 1}, {"name": "b", "id": 2}]}"#;
+        let query =
+            |path: &str| query_json(content, &serde_json_path::JsonPath::parse(path).unwrap());
+
+        assert_eq!(query("$.items[*].name").unwrap(), "[\n  \"a\",\n  \"b\"\n]");
+        assert_eq!(query("$.missing").unwrap(), "[]");
+        assert!(
+            query_json(
+                "<html></html>",
+                &serde_json_path::JsonPath::parse("$").unwrap()
+            )
+            .unwrap_err()
+            .starts_with("The response is not valid JSON")
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_jsonpaths() {
+        let error = fetch(Input {
+            urls: vec!["https://example.com/".to_owned()],
+            jsonpath: Some("$.items[".to_owned()),
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .starts_with("Invalid JSONPath \"$.items[\""),
+            "{}",
+            error
+        );
+    }
+
+    fn total_size(results: &[FetchResult]) -> usize {
+        serde_json::to_string(results).unwrap().len()
+    }
+
+    #[test]
+    fn cuts_the_result_reaching_the_total_size() {
+        let mut results = vec![
+            FetchResult::ok("https://example.com/a", "a".repeat(1000)),
+            FetchResult::ok("https://example.com/b", "b".repeat(1000)),
+        ];
+        let untouched = serialized_len(&results[0]);
+
+        limit_total_size(&mut results, 2000);
+
+        assert!(total_size(&results) <= 2000, "{}", total_size(&results));
+        assert_eq!(serialized_len(&results[0]), untouched);
+        assert!(!results[0].truncated);
+
+        let cut = &results[1];
+        assert!(cut.truncated);
+        assert!(cut.content.starts_with("bbb"));
+        assert!(cut.content.ends_with(CUT_NOTE));
+        assert_eq!(cut.original_length, Some(1000));
+        assert_eq!(cut.returned_length, Some(cut.content.len()));
+
+        // Within the limit, nothing changes
+        let mut results = vec![FetchResult::ok("https://example.com/a", "a".repeat(100))];
+        limit_total_size(&mut results, 2000);
+        assert_eq!(results[0].content.len(), 100);
+        assert!(!results[0].truncated);
+    }
+
+    #[tokio::test]
+    async fn drops_pages_past_the_total_size() {
+        let config = crate::config::Config {
+            fetch_max_total_bytes: Some(2500),
+            ..crate::config::config().clone()
+        };
+
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/{page}",
+            axum::routing::get(|| async {
+                (
+                    [(reqwest::header::CONTENT_TYPE, "text/plain")],
+                    "x".repeat(1000),
+                )
+            }),
+        ))
+        .await;
+
+        let results = fetch_with_cache(
+            Input {
+                urls: (0..4).map(|page| format!("{}/{}", base, page)).collect(),
+                ..Default::default()
+            },
+            true,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert!(total_size(&results) <= 2500, "{}", total_size(&results));
+
+        assert!(!results[0].truncated);
+        assert_eq!(results[0].content.len(), 1000);
+
+        assert!(results[1].content.ends_with(CUT_NOTE));
+
+        for dropped in &results[2..] {
+            assert_eq!(dropped.status, FetchStatus::Ok);
+            assert_eq!(dropped.content, DROPPED_NOTE);
+            assert!(dropped.truncated);
+            assert_eq!(dropped.returned_length, Some(0));
+        }
+    }
+
+    #[test]
+    fn derives_the_mobile_host() {
+        let candidate = |url| mobile_candidate(url).map(|url| url.to_string());
+
+        assert_eq!(
+            candidate("https://www.example.com/a?b=c#d").as_deref(),
+            Some("https://m.example.com/a?b=c#d")
+        );
+        assert_eq!(
+            candidate("http://example.com:8080/").as_deref(),
+            Some("http://m.example.com:8080/")
+        );
+        assert_eq!(candidate("https://m.example.com/"), None);
+        assert_eq!(candidate("http://93.184.216.34/"), None);
+        assert_eq!(candidate("http://[::1]/"), None);
+        assert_eq!(candidate("not a url"), None);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_url_without_a_mobile_site() {
+        assert_eq!(mobile_url("http://nonexistent.invalid/page").await, None);
+        assert_eq!(mobile_url("http://127.0.0.1:1/").await, None);
+    }
+
+    #[tokio::test]
+    async fn returns_markdown_and_plain_text() {
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(|| async {
+                axum::response::Html(
+                    "<h1>Guide</h1><p><strong>Read this first.</strong></p>\
+                     <p><a href=\"/next\">Next page</a></p><ul><li>One</li><li>Two</li></ul>",
+                )
+            }),
+        ))
+        .await;
+
+        let results = fetch(Input {
+            urls: vec![base],
+            dual_output: Some(true),
+            min_content_length: Some(0),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let result = &results[0];
+        assert!(result.content.contains("# Guide"), "{}", result.content);
+        assert!(
+            result.content.contains("**Read this first.**"),
+            "{}",
+            result.content
+        );
+        assert!(
+            result.content.contains("[Next page](/next)"),
+            "{}",
+            result.content
+        );
+
+        let text = result.text.as_deref().unwrap();
+        assert_eq!(text, "Guide\nRead this first.\nNext page\nOne\nTwo");
+    }
+
+    #[test]
+    fn detects_english_and_japanese() {
+        let english = detect_language(
+            "The quick brown fox jumps over the lazy dog while the farmer watches from the porch.",
+        )
+        .unwrap();
+        assert_eq!(english.code, "eng");
+        assert!(english.confidence > 0.5);
+
+        let japanese =
+            detect_language("吾輩は猫である。名前はまだ無い。どこで生れたかとんと見当がつかぬ。")
+                .unwrap();
+        assert_eq!(japanese.code, "jpn");
+        assert!(japanese.confidence > 0.5);
+
+        assert!(detect_language("").is_none());
+    }
+
+    #[tokio::test]
+    async fn returns_unordered_results_with_their_index() {
+        let slow = slow_server(Duration::from_millis(500)).await;
+        let fast = slow_server(Duration::ZERO).await;
+        let urls = vec![slow.clone(), fast.clone(), slow.clone()];
+
+        let results = fetch(Input {
+            urls: urls.clone(),
+            ordered: Some(false),
+            min_content_length: Some(0),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // The fast page completes first; the duplicate slow URL keeps both positions
+        assert_eq!(results[0].url, fast);
+        assert_eq!(results[0].index, Some(1));
+
+        let mut indices: Vec<_> = results.iter().map(|result| result.index.unwrap()).collect();
+        indices.sort();
+        assert_eq!(indices, [0, 1, 2]);
+
+        for result in &results {
+            assert_eq!(result.url, urls[result.index.unwrap()]);
+        }
+
+        let ordered = fetch(Input {
+            urls,
+            min_content_length: Some(0),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert!(ordered.iter().all(|result| result.index.is_none()));
+        assert_eq!(ordered[0].url, slow);
+    }
+
+    #[tokio::test]
+    async fn returns_the_shortest_pages_first() {
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/{length}",
+            axum::routing::get(
+                |axum::extract::Path(length): axum::extract::Path<usize>| async move {
+                    "x".repeat(length)
+                },
+            ),
+        ))
+        .await;
+
+        let results = fetch(Input {
+            urls: [300, 10, 50]
+                .map(|length| format!("{}/{}", base, length))
+                .to_vec(),
+            order: Some(crate::order::Order::ShortestFirst),
+            min_content_length: Some(0),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let lengths: Vec<_> = results.iter().map(|result| result.content.len()).collect();
+        assert_eq!(lengths, [10, 50, 300]);
+    }
+
+    #[test]
+    fn tells_failed_batches() {
+        let ok = FetchResult::ok("https://example.com/", "content".to_owned());
+        let failed = FetchResult::error("https://example.com/404", "HTTP 404".to_owned());
+        let timed_out = FetchResult::timed_out("https://example.com/slow");
+        let download = FetchResult::new(
+            "https://example.com/file.pdf",
+            FetchStatus::Download,
+            String::new(),
+        );
+
+        assert!(all_failed(&[failed.clone(), timed_out.clone()]));
+        assert!(!all_failed(&[failed.clone(), ok.clone()]));
+        assert!(!all_failed(&[ok.clone(), ok]));
+        assert!(!all_failed(&[failed, download]));
+        assert!(!all_failed(&[]));
+    }
+
+    /// Serves `status` with a short page, as bot protection would.
+    async fn status_server(status: reqwest::StatusCode) -> String {
+        crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(move || async move { (status, axum::response::Html("<p>no</p>")) }),
+        ))
+        .await
+    }
+
+    #[tokio::test]
+    async fn never_reaches_the_browser_when_disabled() {
+        let config = crate::config::Config {
+            disable_browser: true,
+            fetch_fallback_chain: vec![Strategy::Reqwest, Strategy::Browser],
+            ..crate::config::config().clone()
+        };
+
+        // A 403 would otherwise be retried with the browser
+        let forbidden = status_server(reqwest::StatusCode::FORBIDDEN).await;
+        let short = slow_server(Duration::ZERO).await;
+
+        let results = fetch_with_cache(
+            Input {
+                urls: vec![forbidden, short],
+                ..Default::default()
+            },
+            true,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results[0].status, FetchStatus::Error);
+        assert_eq!(results[0].http_status, Some(403));
+        assert!(
+            results[0].content.ends_with("reqwest: HTTP 403 Forbidden"),
+            "{}",
+            results[0].content
+        );
+
+        assert_eq!(results[1].status, FetchStatus::Ok);
+        assert!(
+            results[1]
+                .content
+                .contains("the browser fallback is disabled on this server"),
+            "{}",
+            results[1].content
+        );
+    }
+
+    #[tokio::test]
+    async fn times_out_slow_urls_at_the_batch_deadline() {
+        let config = crate::config::Config {
+            fetch_batch_deadline: Some(Duration::from_millis(500)),
+            ..crate::config::config().clone()
+        };
+
+        let fast = slow_server(Duration::ZERO).await;
+        let slow = slow_server(Duration::from_secs(10)).await;
+
+        let started = std::time::Instant::now();
+        let results = fetch_with_cache(
+            Input {
+                urls: vec![slow.clone(), fast.clone()],
+                min_content_length: Some(0),
+                ..Default::default()
+            },
+            true,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        assert_eq!(results[0].url, slow);
+        assert_eq!(results[0].status, FetchStatus::TimedOut);
+        assert!(results[0].content.contains("batch deadline"));
+
+        assert_eq!(results[1].url, fast);
+        assert_eq!(results[1].status, FetchStatus::Ok);
+        assert!(results[1].content.contains("slow"));
+    }
+
+    #[tokio::test]
+    async fn falls_back_on_short_successes_only() {
+        // A second plain request stands for the next source of the chain
+        let config = crate::config::Config {
+            fetch_fallback_chain: vec![Strategy::Reqwest, Strategy::Reqwest],
+            ..crate::config::config().clone()
+        };
+
+        let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let router = axum::Router::new()
+            .route("/ok", axum::routing::get(|| async { "short" }))
+            .route(
+                "/missing",
+                axum::routing::get(|| async { (reqwest::StatusCode::NOT_FOUND, "short") }),
+            )
+            .layer(axum::middleware::from_fn({
+                let hits = hits.clone();
+                move |request: axum::extract::Request, next: axum::middleware::Next| {
+                    hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    next.run(request)
+                }
+            }));
+        let base = crate::test_support::serve(router).await;
+
+        let fetch = |path: &str| {
+            let input = Input {
+                urls: vec![format!("{}{}", base, path)],
+                ..Default::default()
+            };
+            let config = config.clone();
+            async move {
+                fetch_with_cache(input, true, &config)
+                    .await
+                    .unwrap()
+                    .remove(0)
+            }
+        };
+
+        let ok = fetch("/ok").await;
+        assert_eq!(hits.swap(0, std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(ok.status, FetchStatus::Ok);
+        assert_eq!(ok.content, "short");
+
+        let missing = fetch("/missing").await;
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(missing.status, FetchStatus::Error);
+        assert_eq!(missing.http_status, Some(404));
+    }
+
+    #[tokio::test]
+    async fn falls_back_under_the_requested_content_length_only() {
+        // A second plain request stands for the next source of the chain
+        let config = crate::config::Config {
+            fetch_fallback_chain: vec![Strategy::Reqwest, Strategy::Reqwest],
+            ..crate::config::config().clone()
+        };
+
+        let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let router = axum::Router::new().route(
+            "/",
+            axum::routing::get({
+                let hits = hits.clone();
+                move || async move {
+                    hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    "twenty characters!!!"
+                }
+            }),
+        );
+        let base = crate::test_support::serve(router).await;
+
+        // Each threshold is its own cache entry
+        for (min_content_length, expected_hits) in [(21, 2), (20, 1), (19, 1)] {
+            let input = Input {
+                urls: vec![base.clone()],
+                min_content_length: Some(min_content_length),
+                ..Default::default()
+            };
+
+            let result = fetch_with_cache(input, true, &config)
+                .await
+                .unwrap()
+                .remove(0);
+
+            assert_eq!(
+                hits.swap(0, std::sync::atomic::Ordering::SeqCst),
+                expected_hits,
+                "min_content_length {min_content_length}"
+            );
+            assert_eq!(result.status, FetchStatus::Ok);
+            assert_eq!(result.content, "twenty characters!!!");
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_short_pages_without_the_length_check() {
+        // A second plain request stands for the next source of the chain
+        let config = crate::config::Config {
+            fetch_fallback_chain: vec![Strategy::Reqwest, Strategy::Reqwest],
+            ..crate::config::config().clone()
+        };
+
+        let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let router = axum::Router::new().route(
+            "/",
+            axum::routing::get({
+                let hits = hits.clone();
+                move || async move {
+                    hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    "A short answer."
+                }
+            }),
+        );
+        let base = crate::test_support::serve(router).await;
+
+        let results = fetch_with_cache(
+            Input {
+                urls: vec![base],
+                min_content_length: Some(0),
+                ..Default::default()
+            },
+            true,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(results[0].status, FetchStatus::Ok);
+        assert_eq!(results[0].content, "A short answer.");
+    }
+
+    #[tokio::test]
+    async fn records_the_fallback_in_spans() {
+        let config = crate::config::Config {
+            fetch_fallback_chain: vec![Strategy::Reqwest, Strategy::Reqwest],
+            ..crate::config::config().clone()
+        };
+
+        let base = crate::test_support::serve(
+            axum::Router::new().route("/", axum::routing::get(|| async { "short" })),
+        )
+        .await;
+
+        let (spans, _guard) = crate::test_support::capture_spans();
+
+        fetch_with_cache(
+            Input {
+                urls: vec![base.clone()],
+                ..Default::default()
+            },
+            true,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(spans.values("fetch_url", "url"), [base]);
+        assert_eq!(
+            spans.values("fetch_url", "fallback_reason").last().unwrap(),
+            "insufficient_content"
+        );
+        assert_eq!(spans.values("fetch_url", "outcome"), ["Ok"]);
+
+        assert_eq!(spans.values("strategy", "strategy"), ["reqwest", "reqwest"]);
+        assert_eq!(spans.values("strategy", "status"), ["200", "200"]);
+        assert_eq!(
+            spans.values("strategy", "outcome"),
+            ["insufficient_content", "insufficient_content"]
+        );
+    }
+
+    #[tokio::test]
+    async fn tries_the_next_source_on_forbidden_pages() {
+        let config = crate::config::Config {
+            fetch_fallback_chain: vec![Strategy::Reqwest, Strategy::Reqwest],
+            ..crate::config::config().clone()
+        };
+
+        let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let router = axum::Router::new().route(
+            "/",
+            axum::routing::get({
+                let hits = hits.clone();
+                move || async move {
+                    hits.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    reqwest::StatusCode::FORBIDDEN
+                }
+            }),
+        );
+        let base = crate::test_support::serve(router).await;
+
+        let results = fetch_with_cache(
+            Input {
+                urls: vec![base],
+                ..Default::default()
+            },
+            true,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(results[0].status, FetchStatus::Error);
+        assert_eq!(results[0].http_status, Some(403));
+    }
+
+    /// Launches `launches` concurrent first fetches of a browser, each launch
+    /// taking a while and yielding `result`. Returns the launch count and the
+    /// results.
+    async fn launch_concurrently(
+        launches: usize,
+        result: Result<u32, &'static str>,
+    ) -> (usize, Vec<Result<u32, Error>>) {
+        let state = tokio::sync::Mutex::new(None);
+        let count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let results = futures::future::join_all((0..launches).map(|_| {
+            let count = count.clone();
+
+            get_or_launch(&state, move || {
+                count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(100));
+                result.map_err(Error::from)
+            })
+        }))
+        .await;
+
+        (count.load(std::sync::atomic::Ordering::SeqCst), results)
+    }
+
+    #[tokio::test]
+    async fn launches_the_browser_once_for_concurrent_first_fetches() {
+        let (launches, results) = launch_concurrently(8, Ok(1)).await;
+
+        assert_eq!(launches, 1);
+        assert!(results.iter().all(|result| matches!(result, Ok(1))));
+    }
+
+    #[tokio::test]
+    async fn reports_a_failed_launch_to_concurrent_callers() {
+        let (launches, results) = launch_concurrently(8, Err("no Chrome")).await;
+
+        // The callers waiting on the failed launch get its error within the cooldown
+        assert_eq!(launches, 1);
+        for result in results {
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "launching browser: no Chrome"
+            );
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use serde::Serialize;
+
+/// Readability metrics of a text. The formulas are calibrated for English;
+/// the scores of other languages are only indicative.
+#[derive(Debug, Clone, Serialize)]
+pub struct Readability {
+    pub words: usize,
+    pub sentences: usize,
+    pub syllables: usize,
+    /// 0 (very difficult) to 100 (very easy); 60-70 is plain English.
+    pub flesch_reading_ease: f64,
+    /// The U.S. school grade needed to understand the text.
+    pub flesch_kincaid_grade: f64,
+    /// A grade level based on characters per word.
+    pub automated_readability_index: f64,
+    /// A grade level based on letters and sentences per 100 words.
+    pub coleman_liau_index: f64,
+}
+
+/// Computes the readability metrics of a plain text, if it has any words.
+pub(crate) fn readability(text: &str) -> Option<Readability> {
+    let words: Vec<&str> = text
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| word.chars().any(char::is_alphabetic))
+        .collect();
+
+    if words.is_empty() {
+        return None;
+    }
+
+    let sentences = count_sentences(text).max(1);
+    let syllables: usize = words.iter().map(|word| count_syllables(word)).sum();
+    let letters: usize = words
+        .iter()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).count())
+        .sum();
+
+    let word_count = words.len() as f64;
+    let words_per_sentence = word_count / sentences as f64;
+    let syllables_per_word = syllables as f64 / word_count;
+    let letters_per_word = letters as f64 / word_count;
+
+    Some(Readability {
+        words: words.len(),
+        sentences,
+        syllables,
+        flesch_reading_ease: round(
+            206.835 - 1.015 * words_per_sentence - 84.6 * syllables_per_word,
+        ),
+        flesch_kincaid_grade: round(0.39 * words_per_sentence + 11.8 * syllables_per_word - 15.59),
+        automated_readability_index: round(
+            4.71 * letters_per_word + 0.5 * words_per_sentence - 21.43,
+        ),
+        coleman_liau_index: round(
+            0.0588 * (letters_per_word * 100.0) - 0.296 * (100.0 / words_per_sentence) - 15.8,
+        ),
+    })
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Counts the runs of terminal punctuation, and lines without any (such as
+/// headings and list items) as sentences of their own.
+fn count_sentences(text: &str) -> usize {
+    text.lines()
+        .filter(|line| line.chars().any(char::is_alphabetic))
+        .map(|line| {
+            let terminators = line
+                .split(['.', '!', '?'])
+                .filter(|part| part.chars().any(char::is_alphabetic))
+                .count();
+
+            terminators.max(1)
+        })
+        .sum()
+}
+
+/// Estimates the syllables of a word from its groups of vowels.
+fn count_syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+
+    let mut count = 0;
+    let mut previous_vowel = false;
+
+    for c in word.chars() {
+        let vowel = matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+
+    // A final silent `e`, as in "make", but not "the" or "table"
+    if word.ends_with('e') && !word.ends_with("le") && count > 1 {
+        count -= 1;
+    }
+
+    count.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 0.5,
+            "{actual} is not about {expected}"
+        );
+    }
+
+    #[test]
+    fn scores_simple_text() {
+        let metrics = readability("The cat sat on the mat. The dog ran to the park.").unwrap();
+
+        assert_eq!(metrics.words, 12);
+        assert_eq!(metrics.sentences, 2);
+        assert_eq!(metrics.syllables, 12);
+        // 206.835 - 1.015 * 6 - 84.6 * 1
+        assert_near(metrics.flesch_reading_ease, 116.1);
+        // 0.39 * 6 + 11.8 * 1 - 15.59
+        assert_near(metrics.flesch_kincaid_grade, -1.4);
+    }
+
+    #[test]
+    fn scores_dense_text_as_harder() {
+        let simple = readability("The cat sat on the mat. The dog ran to the park.").unwrap();
+        let dense = readability(
+            "Notwithstanding considerable methodological heterogeneity, the longitudinal \
+             investigation demonstrated statistically significant associations between \
+             socioeconomic characteristics and educational attainment.",
+        )
+        .unwrap();
+
+        assert!(dense.flesch_reading_ease < 0.0, "{dense:?}");
+        assert!(dense.flesch_kincaid_grade > 16.0, "{dense:?}");
+        assert!(dense.automated_readability_index > simple.automated_readability_index);
+        assert!(dense.coleman_liau_index > simple.coleman_liau_index);
+    }
+
+    #[test]
+    fn counts_syllables_and_sentences() {
+        assert_eq!(count_syllables("make"), 1);
+        assert_eq!(count_syllables("table"), 2);
+        assert_eq!(count_syllables("the"), 1);
+        assert_eq!(count_syllables("readability"), 5);
+        // Headings and list items without punctuation are sentences of their own
+        assert_eq!(count_sentences("Title\nOne. Two!\n- item"), 4);
+        assert!(readability("42 ...").is_none());
+    }
+}
@@ -0,0 +1,283 @@
+use rmcp::schemars::JsonSchema;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::tool::fetch::Error;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The URL of the page whose forms to extract.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Form {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    /// The URL the form submits to, resolved against the page URL.
+    pub action: String,
+    /// `GET` or `POST`.
+    pub method: String,
+    pub enctype: Option<String>,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Field {
+    /// `input`, `select`, `textarea` or `button`.
+    pub tag: String,
+    pub name: Option<String>,
+    /// The `type` of an input or button, e.g. `email` or `submit`.
+    #[serde(rename = "type")]
+    pub field_type: Option<String>,
+    pub label: Option<String>,
+    pub required: bool,
+    pub value: Option<String>,
+    pub placeholder: Option<String>,
+    /// The choices of a `select`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<SelectOption>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelectOption {
+    pub value: String,
+    pub label: String,
+    pub selected: bool,
+}
+
+fn text_of(element: ElementRef) -> String {
+    element
+        .text()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn attr(element: ElementRef, name: &str) -> Option<String> {
+    element
+        .value()
+        .attr(name)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+}
+
+/// Finds the label of a field: a `<label for>` pointing at its id, an
+/// enclosing `<label>`, or else its `aria-label`.
+fn label_of(document: &Html, field: ElementRef) -> Option<String> {
+    let label_selector = Selector::parse("label").unwrap();
+
+    let by_id = field.value().id().and_then(|id| {
+        document
+            .select(&label_selector)
+            .find(|label| label.value().attr("for") == Some(id))
+    });
+
+    let enclosing = || {
+        field
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .find(|ancestor| ancestor.value().name() == "label")
+    };
+
+    by_id
+        .or_else(enclosing)
+        .map(text_of)
+        .filter(|label| !label.is_empty())
+        .or_else(|| attr(field, "aria-label"))
+}
+
+fn extract_field(document: &Html, element: ElementRef) -> Field {
+    let tag = element.value().name();
+
+    let field_type = match tag {
+        "input" => Some(attr(element, "type").map_or("text".to_owned(), |t| t.to_lowercase())),
+        "button" => Some(attr(element, "type").map_or("submit".to_owned(), |t| t.to_lowercase())),
+        _ => None,
+    };
+
+    let value = match tag {
+        "textarea" => Some(element.text().collect::<String>()).filter(|text| !text.is_empty()),
+        "button" => attr(element, "value").or_else(|| Some(text_of(element))),
+        _ => attr(element, "value"),
+    };
+
+    let options = (tag == "select").then(|| {
+        let option_selector = Selector::parse("option").unwrap();
+
+        element
+            .select(&option_selector)
+            .map(|option| {
+                let label = text_of(option);
+                SelectOption {
+                    value: option
+                        .value()
+                        .attr("value")
+                        .map_or(label.clone(), str::to_owned),
+                    label,
+                    selected: option.value().attr("selected").is_some(),
+                }
+            })
+            .collect()
+    });
+
+    Field {
+        tag: tag.to_owned(),
+        name: attr(element, "name"),
+        field_type,
+        label: label_of(document, element),
+        required: element.value().attr("required").is_some(),
+        value,
+        placeholder: attr(element, "placeholder"),
+        options,
+    }
+}
+
+/// Extracts every `<form>` of a document with its fields, resolving the
+/// actions against `base`.
+fn extract_forms(base: &reqwest::Url, html: &str) -> Vec<Form> {
+    let document = Html::parse_document(html);
+
+    let form_selector = Selector::parse("form").unwrap();
+    let field_selector = Selector::parse("input, select, textarea, button").unwrap();
+
+    document
+        .select(&form_selector)
+        .map(|form| {
+            // A missing or empty action submits to the page itself
+            let action = attr(form, "action")
+                .and_then(|action| base.join(&action).ok())
+                .unwrap_or_else(|| base.clone());
+
+            let method = match attr(form, "method") {
+                Some(method) if method.eq_ignore_ascii_case("post") => "POST",
+                _ => "GET",
+            };
+
+            Form {
+                id: attr(form, "id"),
+                name: attr(form, "name"),
+                action: action.to_string(),
+                method: method.to_owned(),
+                enctype: attr(form, "enctype"),
+                fields: form
+                    .select(&field_selector)
+                    .map(|field| extract_field(&document, field))
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+pub async fn forms(Input { url }: Input) -> Result<Vec<Form>, Error> {
+    let client = crate::tool::fetch::http_client()?;
+
+    tracing::info!("Fetching forms: {}", url);
+
+    let (final_url, html) = crate::tool::fetch::fetch_html(&client, &url).await?;
+
+    Ok(extract_forms(&final_url, &html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><body>
+        <form id="signup" action="/account/new" method="post" enctype="multipart/form-data">
+            <label for="email">Email address</label>
+            <input id="email" name="email" type="EMAIL" required placeholder="you@example.com">
+            <label>Password <input name="password" type="password" required></label>
+            <input name="csrf" type="hidden" value="token">
+            <select name="plan" aria-label="Plan">
+                <option value="free">Free</option>
+                <option value="pro" selected>Pro  plan</option>
+                <option>Team</option>
+            </select>
+            <textarea name="bio" placeholder="About you">Hello</textarea>
+            <button>Sign up</button>
+        </form>
+        <form><input name="q"></form>
+    </body></html>"#;
+
+    fn field<'a>(form: &'a Form, name: &str) -> &'a Field {
+        form.fields
+            .iter()
+            .find(|field| field.name.as_deref() == Some(name))
+            .unwrap()
+    }
+
+    #[test]
+    fn enumerates_fields_with_their_attributes() {
+        let base = reqwest::Url::parse("https://example.com/signup").unwrap();
+
+        let forms = extract_forms(&base, PAGE);
+        assert_eq!(forms.len(), 2);
+
+        let signup = &forms[0];
+        assert_eq!(signup.id.as_deref(), Some("signup"));
+        assert_eq!(signup.action, "https://example.com/account/new");
+        assert_eq!(signup.method, "POST");
+        assert_eq!(signup.enctype.as_deref(), Some("multipart/form-data"));
+        assert_eq!(signup.fields.len(), 6);
+
+        let email = field(signup, "email");
+        assert_eq!(email.tag, "input");
+        assert_eq!(email.field_type.as_deref(), Some("email"));
+        assert_eq!(email.label.as_deref(), Some("Email address"));
+        assert_eq!(email.placeholder.as_deref(), Some("you@example.com"));
+        assert!(email.required);
+
+        let password = field(signup, "password");
+        assert_eq!(password.label.as_deref(), Some("Password"));
+        assert!(password.required);
+
+        let csrf = field(signup, "csrf");
+        assert_eq!(csrf.field_type.as_deref(), Some("hidden"));
+        assert_eq!(csrf.value.as_deref(), Some("token"));
+        assert!(!csrf.required);
+
+        let plan = field(signup, "plan");
+        assert_eq!(plan.tag, "select");
+        assert_eq!(plan.label.as_deref(), Some("Plan"));
+        let options: Vec<_> = plan
+            .options
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|option| {
+                (
+                    option.value.as_str(),
+                    option.label.as_str(),
+                    option.selected,
+                )
+            })
+            .collect();
+        assert_eq!(
+            options,
+            [
+                ("free", "Free", false),
+                ("pro", "Pro plan", true),
+                ("Team", "Team", false)
+            ]
+        );
+
+        let bio = field(signup, "bio");
+        assert_eq!(bio.tag, "textarea");
+        assert_eq!(bio.value.as_deref(), Some("Hello"));
+        assert!(bio.options.is_none());
+
+        let button = signup.fields.last().unwrap();
+        assert_eq!(button.tag, "button");
+        assert_eq!(button.field_type.as_deref(), Some("submit"));
+        assert_eq!(button.value.as_deref(), Some("Sign up"));
+
+        // Without an action or method, a form is sent to the page with GET
+        assert_eq!(forms[1].action, "https://example.com/signup");
+        assert_eq!(forms[1].method, "GET");
+        assert_eq!(field(&forms[1], "q").field_type.as_deref(), Some("text"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Reports when pages last changed from their headers alone, so that agents
+//! can decide what to re-fetch without downloading any body.
+
+use futures::StreamExt;
+use rmcp::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::tool::fetch::Error;
+
+/// Upper bound of the URLs checked in one call.
+const MAX_URLS: usize = 100;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The URLs to check (at most 100).
+    pub urls: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Freshness {
+    pub url: String,
+    /// The HTTP status of the response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// The `Last-Modified` header, e.g. `Wed, 21 Oct 2015 07:28:00 GMT`.
+    pub last_modified: Option<String>,
+    /// The `ETag` header, which changes whenever the page does.
+    pub etag: Option<String>,
+    /// Why the URL couldn't be checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn header(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+async fn check(client: &reqwest::Client, url: &str) -> Result<reqwest::Response, Error> {
+    let target = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL {:?}: {}", url, e))?;
+    crate::ssrf::check_target(&target).await?;
+
+    let response = crate::tool::fetch::head(client, url).send().await?;
+
+    // Servers not supporting HEAD get a GET, whose body is never read
+    if matches!(
+        response.status(),
+        reqwest::StatusCode::METHOD_NOT_ALLOWED | reqwest::StatusCode::NOT_IMPLEMENTED
+    ) {
+        return Ok(crate::tool::fetch::get(client, url).send().await?);
+    }
+
+    Ok(response)
+}
+
+pub async fn last_modified(Input { urls }: Input) -> Result<Vec<Freshness>, Error> {
+    if urls.len() > MAX_URLS {
+        return Err(format!("At most {} URLs can be checked at once", MAX_URLS).into());
+    }
+
+    let client = crate::tool::fetch::http_client()?;
+
+    let _permit = crate::tool::fetch::fetch_permit().await?;
+
+    tracing::info!("Checking last-modified dates of {} URLs", urls.len());
+
+    let results = futures::stream::iter(urls)
+        .map(|url| {
+            let client = client.clone();
+
+            async move {
+                match check(&client, &url).await {
+                    Ok(response) => Freshness {
+                        url,
+                        status: Some(response.status().as_u16()),
+                        last_modified: header(&response, reqwest::header::LAST_MODIFIED),
+                        etag: header(&response, reqwest::header::ETAG),
+                        error: None,
+                    },
+                    Err(e) => Freshness {
+                        url,
+                        status: None,
+                        last_modified: None,
+                        etag: None,
+                        error: Some(e.to_string()),
+                    },
+                }
+            }
+        })
+        .buffered(crate::config::config().fetch_url_concurrency)
+        .collect()
+        .await;
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_last_modified_and_etag() {
+        let base = crate::test_support::serve(
+            axum::Router::new()
+                .route(
+                    "/dated",
+                    axum::routing::get(|| async {
+                        (
+                            [
+                                ("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+                                ("etag", "\"abc123\""),
+                            ],
+                            "page",
+                        )
+                    }),
+                )
+                .route("/plain", axum::routing::get(|| async { "page" })),
+        )
+        .await;
+
+        let results = last_modified(Input {
+            urls: vec![format!("{}/dated", base), format!("{}/plain", base)],
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(results[0].status, Some(200));
+        assert_eq!(
+            results[0].last_modified.as_deref(),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+        assert_eq!(results[0].etag.as_deref(), Some("\"abc123\""));
+        assert!(results[0].error.is_none());
+
+        assert_eq!(results[1].status, Some(200));
+        assert!(results[1].last_modified.is_none());
+        assert!(results[1].etag.is_none());
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_get_when_head_is_not_allowed() {
+        let methods = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (head_methods, get_methods) = (methods.clone(), methods.clone());
+
+        let base = crate::test_support::serve(
+            axum::Router::new().route(
+                "/",
+                axum::routing::head(move || async move {
+                    head_methods.lock().unwrap().push("HEAD");
+                    reqwest::StatusCode::METHOD_NOT_ALLOWED
+                })
+                .get(move || async move {
+                    get_methods.lock().unwrap().push("GET");
+                    ([("etag", "\"from-get\"")], "page")
+                }),
+            ),
+        )
+        .await;
+
+        let results = last_modified(Input { urls: vec![base] }).await.unwrap();
+
+        assert_eq!(*methods.lock().unwrap(), ["HEAD", "GET"]);
+        assert_eq!(results[0].status, Some(200));
+        assert_eq!(results[0].etag.as_deref(), Some("\"from-get\""));
+    }
+
+    #[tokio::test]
+    async fn rejects_more_than_the_maximum_of_urls() {
+        let urls = vec!["https://example.com/".to_owned(); MAX_URLS + 1];
+
+        let error = last_modified(Input { urls }).await.unwrap_err();
+
+        assert!(error.to_string().contains("At most 100 URLs"));
+    }
+}
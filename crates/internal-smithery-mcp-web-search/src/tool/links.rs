@@ -0,0 +1,166 @@
+use rmcp::schemars::JsonSchema;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::tool::fetch::Error;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The URL of the page whose links to list.
+    pub url: String,
+
+    /// If `true`, returns only the links to the host of the page
+    /// (default: `false`).
+    #[serde(default)]
+    pub internal_only: bool,
+
+    /// If `true`, returns only the links to other hosts (default: `false`).
+    #[serde(default)]
+    pub external_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkResult {
+    /// The `title` attribute of the link.
+    pub title: Option<String>,
+    /// The target of the link, resolved against the page URL.
+    pub url: String,
+    pub text: String,
+}
+
+/// Extracts the `http` and `https` links of a document, once per target, with
+/// whether they point at the host of `base`.
+fn extract_links(base: &reqwest::Url, html: &str) -> Vec<(LinkResult, bool)> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("a[href]").unwrap();
+
+    let mut seen = std::collections::HashSet::new();
+
+    document
+        .select(&selector)
+        .filter_map(|a| {
+            let url = base.join(a.value().attr("href")?.trim()).ok()?;
+
+            if !matches!(url.scheme(), "http" | "https") || !seen.insert(url.to_string()) {
+                return None;
+            }
+
+            let internal = url.host_str() == base.host_str();
+
+            let link = LinkResult {
+                title: a
+                    .value()
+                    .attr("title")
+                    .map(str::trim)
+                    .filter(|title| !title.is_empty())
+                    .map(str::to_owned),
+                url: url.to_string(),
+                text: a
+                    .text()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            };
+
+            Some((link, internal))
+        })
+        .collect()
+}
+
+/// Fetches the HTML of a page with reqwest, falling back to the browser when
+/// the request fails or the page has no links, as with pages rendered by
+/// JavaScript.
+async fn fetch_links(url: &str) -> Result<Vec<(LinkResult, bool)>, Error> {
+    let client = crate::tool::fetch::http_client()?;
+
+    let fetched = crate::tool::fetch::fetch_html(&client, url)
+        .await
+        .map(|(final_url, html)| extract_links(&final_url, &html));
+
+    if !should_render(&fetched) || crate::config::config().disable_browser {
+        return fetched;
+    }
+
+    match crate::tool::fetch::fetch_rendered_html(url).await {
+        Ok((final_url, html)) => Ok(extract_links(&final_url, &html)),
+        Err(e) => {
+            tracing::warn!("Failed to render {} in the browser: {}", url, e);
+            fetched
+        }
+    }
+}
+
+/// Whether the outcome of the plain request calls for rendering the page in
+/// the browser. A blocked target never does, even when reqwest found it on
+/// a redirect: the browser would follow the same redirect.
+fn should_render(fetched: &Result<Vec<(LinkResult, bool)>, Error>) -> bool {
+    match fetched {
+        Ok(links) => links.is_empty(),
+        Err(e) => crate::ssrf::Blocked::find(e.as_ref()).is_none(),
+    }
+}
+
+pub async fn links(
+    Input {
+        url,
+        internal_only,
+        external_only,
+    }: Input,
+) -> Result<Vec<LinkResult>, Error> {
+    if internal_only && external_only {
+        return Err("internal_only and external_only cannot both be set".into());
+    }
+
+    tracing::info!("Fetching links: {}", url);
+
+    let links = fetch_links(&url).await?;
+
+    Ok(links
+        .into_iter()
+        .filter(|(_, internal)| match (internal_only, external_only) {
+            (true, _) => *internal,
+            (_, true) => !*internal,
+            _ => true,
+        })
+        .map(|(link, _)| link)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_redirects_to_internal_targets_without_fallback() {
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(|| async {
+                axum::response::Redirect::temporary("http://127.0.0.1:1/")
+            }),
+        ))
+        .await;
+
+        let error = links(Input {
+            url: base,
+            internal_only: false,
+            external_only: false,
+        })
+        .await
+        .unwrap_err();
+
+        assert!(
+            crate::ssrf::Blocked::find(error.as_ref()).is_some(),
+            "{}",
+            error
+        );
+        assert!(!should_render(&Err(error)));
+    }
+
+    #[test]
+    fn renders_pages_without_links() {
+        assert!(should_render(&Ok(vec![])));
+        assert!(should_render(&Err("connection refused".into())));
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+
+use rmcp::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::tool::fetch::Error;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The URL of the page to inspect.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Metadata {
+    pub url: String,
+    pub title: Option<String>,
+    /// The `og:title`, falling back to the `twitter:title`.
+    pub og_title: Option<String>,
+    /// The `description` meta tag, falling back to the OpenGraph one.
+    pub description: Option<String>,
+    /// The `og:description`, falling back to the `twitter:description`.
+    pub og_description: Option<String>,
+    /// The preview image of the page (`og:image` or `twitter:image`),
+    /// resolved against the page URL.
+    pub og_image: Option<String>,
+    /// The canonical URL, resolved against the page URL.
+    pub canonical_url: Option<String>,
+    pub author: Option<String>,
+    /// When the page was published, as given by `article:published_time`
+    /// or a similar tag.
+    pub published_time: Option<String>,
+    /// OpenGraph fields keyed without the `og:` prefix, e.g. `title`, `image`.
+    pub open_graph: BTreeMap<String, String>,
+    /// Twitter card fields keyed without the `twitter:` prefix, e.g. `card`.
+    pub twitter: BTreeMap<String, String>,
+    /// Every `<meta>` tag keyed by its `name`, `property`, `http-equiv` or `itemprop`.
+    pub meta: BTreeMap<String, String>,
+}
+
+/// Extracts the metadata found in the `<head>` of an HTML document.
+pub(crate) fn extract_metadata(url: &str, html: &str) -> Metadata {
+    let document = scraper::Html::parse_document(html);
+
+    let mut metadata = Metadata {
+        url: url.to_owned(),
+        ..Default::default()
+    };
+
+    let title_selector = scraper::Selector::parse("title").unwrap();
+    metadata.title = document
+        .select(&title_selector)
+        .next()
+        .map(|title| title.text().collect::<String>().trim().to_owned())
+        .filter(|title| !title.is_empty());
+
+    let base = reqwest::Url::parse(url).ok();
+    let resolve = |href: &str| match &base {
+        Some(base) => base
+            .join(href)
+            .map_or(href.to_owned(), |url| url.to_string()),
+        None => href.to_owned(),
+    };
+
+    let canonical_selector = scraper::Selector::parse("link[rel~='canonical'][href]").unwrap();
+    metadata.canonical_url = document
+        .select(&canonical_selector)
+        .next()
+        .and_then(|link| link.value().attr("href"))
+        .map(|href| resolve(href.trim()));
+
+    let meta_selector = scraper::Selector::parse("meta[content]").unwrap();
+    for meta in document.select(&meta_selector) {
+        let element = meta.value();
+
+        let Some(key) = ["property", "name", "http-equiv", "itemprop"]
+            .iter()
+            .find_map(|attr| element.attr(attr))
+        else {
+            continue;
+        };
+
+        let key = key.trim().to_ascii_lowercase();
+        let content = element
+            .attr("content")
+            .unwrap_or_default()
+            .trim()
+            .to_owned();
+
+        if let Some(field) = key.strip_prefix("og:") {
+            metadata
+                .open_graph
+                .entry(field.to_owned())
+                .or_insert_with(|| content.clone());
+        } else if let Some(field) = key.strip_prefix("twitter:") {
+            metadata
+                .twitter
+                .entry(field.to_owned())
+                .or_insert_with(|| content.clone());
+        }
+
+        metadata.meta.entry(key).or_insert(content);
+    }
+
+    // The first non-empty value among the given `meta` keys
+    let first = |keys: &[&str]| {
+        keys.iter()
+            .filter_map(|key| metadata.meta.get(*key))
+            .find(|value| !value.is_empty())
+            .cloned()
+    };
+
+    metadata.og_title = first(&["og:title", "twitter:title"]);
+    metadata.description = first(&["description", "og:description", "twitter:description"]);
+    metadata.og_description = first(&["og:description", "twitter:description"]);
+    metadata.og_image = first(&[
+        "og:image",
+        "og:image:url",
+        "twitter:image",
+        "twitter:image:src",
+    ])
+    .map(|image| resolve(&image));
+    metadata.author = first(&["author", "article:author", "twitter:creator", "dc.creator"]);
+    metadata.published_time = first(&[
+        "article:published_time",
+        "og:published_time",
+        "datepublished",
+        "dc.date",
+        "date",
+    ]);
+
+    metadata
+}
+
+pub async fn metadata(Input { url }: Input) -> Result<Metadata, Error> {
+    let client = crate::tool::fetch::http_client()?;
+
+    tracing::info!("Fetching metadata: {}", url);
+
+    let target = reqwest::Url::parse(&url).map_err(|e| format!("Invalid URL {:?}: {}", url, e))?;
+    crate::ssrf::check_target(&target).await?;
+
+    // A HEAD request first, so that e.g. a PDF or a video isn't downloaded
+    // only to find it has no `<head>`. Servers that don't support HEAD are
+    // given the benefit of the doubt.
+    if let Ok(response) = crate::tool::fetch::head(&client, &url).send().await
+        && response.status().is_success()
+        && let Some(content_type) = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+        && !content_type.contains("html")
+    {
+        return Err(format!(
+            "{} is not an HTML page (Content-Type: {})",
+            url, content_type
+        )
+        .into());
+    }
+
+    let (final_url, html) = crate::tool::fetch::fetch_html(&client, &url).await?;
+
+    // Relative URLs of the page resolve against where it was served from
+    Ok(extract_metadata(final_url.as_str(), &html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn refuses_internal_targets() {
+        let error = metadata(Input {
+            url: "http://169.254.169.254/latest/meta-data/".to_owned(),
+        })
+        .await
+        .unwrap_err();
+
+        assert!(error.is::<crate::ssrf::Blocked>());
+    }
+
+    const PAGE: &str = r#"<html><head>
+        <title> Title </title>
+        <meta name="description" content="What the page is about">
+        <meta name="author" content="Jane Doe">
+        <link rel="canonical" href="/articles/1">
+        <meta property="og:title" content="OpenGraph title">
+        <meta property="og:image" content="/cover.png">
+        <meta property="article:published_time" content="2024-05-01T09:00:00Z">
+        <meta name="twitter:card" content="summary_large_image">
+        <meta name="twitter:description" content="The Twitter description">
+        <meta http-equiv="content-language" content="en">
+    </head><body></body></html>"#;
+
+    #[test]
+    fn extracts_every_field() {
+        let metadata = extract_metadata("https://example.com/articles/1?ref=feed", PAGE);
+
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+        assert_eq!(metadata.og_title.as_deref(), Some("OpenGraph title"));
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("What the page is about")
+        );
+        // Without `og:description`, the Twitter one stands in
+        assert_eq!(
+            metadata.og_description.as_deref(),
+            Some("The Twitter description")
+        );
+        assert_eq!(
+            metadata.canonical_url.as_deref(),
+            Some("https://example.com/articles/1")
+        );
+        assert_eq!(
+            metadata.og_image.as_deref(),
+            Some("https://example.com/cover.png")
+        );
+        assert_eq!(metadata.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(
+            metadata.published_time.as_deref(),
+            Some("2024-05-01T09:00:00Z")
+        );
+
+        assert_eq!(
+            metadata.twitter.get("card").map(String::as_str),
+            Some("summary_large_image")
+        );
+        assert_eq!(
+            metadata.twitter.get("description").map(String::as_str),
+            Some("The Twitter description")
+        );
+        assert_eq!(
+            metadata.open_graph.get("image").map(String::as_str),
+            Some("/cover.png")
+        );
+        assert_eq!(
+            metadata.meta.get("content-language").map(String::as_str),
+            Some("en")
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_metadata_of_pages() {
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/",
+            axum::routing::get(|| async { axum::response::Html(PAGE) }),
+        ))
+        .await;
+
+        let metadata = metadata(Input { url: base.clone() }).await.unwrap();
+
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+        assert_eq!(metadata.og_image, Some(format!("{base}/cover.png")));
+        assert_eq!(metadata.canonical_url, Some(format!("{base}/articles/1")));
+    }
+
+    #[tokio::test]
+    async fn resolves_against_the_final_url() {
+        let base = crate::test_support::serve(
+            axum::Router::new()
+                .route(
+                    "/old",
+                    axum::routing::get(|| async { axum::response::Redirect::to("/docs/page") }),
+                )
+                .route(
+                    "/docs/page",
+                    axum::routing::get(|| async {
+                        axum::response::Html(
+                            r#"<html><head><link rel="canonical" href="page">
+                            <meta property="og:image" content="cover.png"></head></html>"#,
+                        )
+                    }),
+                ),
+        )
+        .await;
+
+        let metadata = metadata(Input {
+            url: format!("{base}/old"),
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(metadata.url, format!("{base}/docs/page"));
+        assert_eq!(metadata.canonical_url, Some(format!("{base}/docs/page")));
+        assert_eq!(metadata.og_image, Some(format!("{base}/docs/cover.png")));
+    }
+}
@@ -1,2 +1,12 @@
+pub mod comments;
 pub mod fetch;
+pub mod forms;
+pub mod last_modified;
+pub mod links;
+pub mod metadata;
+pub mod monitor;
+pub mod robots;
+pub mod screenshot;
 pub mod search;
+pub mod snapshot;
+pub mod structured;
@@ -0,0 +1,124 @@
+use rmcp::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::tool::fetch::{Error, FetchStatus};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The URL of the page to monitor.
+    pub url: String,
+
+    /// The `hash` returned by a previous call. If specified, `changed` tells
+    /// whether the content differs since then.
+    pub previous_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Monitor {
+    pub url: String,
+    /// SHA-256 of the content, in hex. Whitespace differences are ignored.
+    pub hash: String,
+    /// Whether the hash differs from `previous_hash`, when given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed: Option<bool>,
+    pub content: String,
+}
+
+/// Hashes content, ignoring differences in whitespace.
+fn content_hash(content: &str) -> String {
+    let mut hasher = Sha256::new();
+
+    for word in content.split_whitespace() {
+        hasher.update(word.as_bytes());
+        hasher.update(b" ");
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+pub async fn monitor(Input { url, previous_hash }: Input) -> Result<Monitor, Error> {
+    // A cached page would hide any change made since it was fetched
+    let result = crate::tool::fetch::fetch_fresh(crate::tool::fetch::Input {
+        urls: vec![url.clone()],
+        ..Default::default()
+    })
+    .await?
+    .into_iter()
+    .next()
+    .ok_or("no fetch result")?;
+
+    if result.status != FetchStatus::Ok {
+        return Err(result.content.into());
+    }
+
+    let hash = content_hash(&result.content);
+
+    Ok(Monitor {
+        url,
+        changed: previous_hash.map(|previous| !previous.trim().eq_ignore_ascii_case(&hash)),
+        hash,
+        content: result.content,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_whitespace_differences() {
+        assert_eq!(content_hash("a  b\n\nc"), content_hash(" a b c "));
+        assert_ne!(content_hash("a b c"), content_hash("a b d"));
+    }
+
+    #[tokio::test]
+    async fn detects_changes_between_fetches() {
+        let version = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+        let router = axum::Router::new().route(
+            "/",
+            axum::routing::get({
+                let version = version.clone();
+                move || async move {
+                    let version = version.load(std::sync::atomic::Ordering::SeqCst);
+                    (
+                        [(reqwest::header::CONTENT_TYPE, "text/plain")],
+                        format!("version {version} ").repeat(50),
+                    )
+                }
+            }),
+        );
+
+        let url = crate::test_support::serve(router).await;
+
+        let first = monitor(Input {
+            url: url.clone(),
+            previous_hash: None,
+        })
+        .await
+        .unwrap();
+
+        let unchanged = monitor(Input {
+            url: url.clone(),
+            previous_hash: Some(first.hash.clone()),
+        })
+        .await
+        .unwrap();
+        assert_eq!(unchanged.changed, Some(false));
+
+        version.store(1, std::sync::atomic::Ordering::SeqCst);
+
+        let changed = monitor(Input {
+            url,
+            previous_hash: Some(first.hash),
+        })
+        .await
+        .unwrap();
+        assert_eq!(changed.changed, Some(true));
+    }
+}
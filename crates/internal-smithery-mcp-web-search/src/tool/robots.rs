@@ -0,0 +1,364 @@
+use rmcp::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::tool::fetch::Error;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The domain whose `robots.txt` to inspect, e.g. `example.com`.
+    pub domain: String,
+
+    /// The user agent to evaluate the rules for. Defaults to `*`.
+    pub user_agent: Option<String>,
+
+    /// A path to check against the rules, e.g. `/search?q=rust`. The result
+    /// then tells whether it is `allowed`.
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Robots {
+    pub robots_url: String,
+    pub user_agent: String,
+    /// `false` when the site has no `robots.txt`, in which case everything is allowed.
+    pub found: bool,
+    pub allow: Vec<String>,
+    pub disallow: Vec<String>,
+    /// Seconds to wait between requests.
+    pub crawl_delay: Option<f64>,
+    pub sitemaps: Vec<String>,
+    /// Whether the requested `path` may be crawled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<bool>,
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    allow: Vec<String>,
+    disallow: Vec<String>,
+    crawl_delay: Option<f64>,
+}
+
+/// Parses `robots.txt` into its groups and the sitemaps it lists.
+fn parse(robots_txt: &str) -> (Vec<Group>, Vec<String>) {
+    let mut groups: Vec<Group> = vec![];
+    let mut sitemaps = vec![];
+
+    // Consecutive `User-agent` lines share the rules that follow them
+    let mut reading_agents = false;
+
+    for line in robots_txt.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+
+        match key.as_str() {
+            "user-agent" => {
+                if !reading_agents {
+                    groups.push(Group::default());
+                }
+                if let Some(group) = groups.last_mut() {
+                    group.agents.push(value.to_ascii_lowercase());
+                }
+                reading_agents = true;
+            }
+            "allow" | "disallow" | "crawl-delay" => {
+                reading_agents = false;
+
+                let Some(group) = groups.last_mut() else {
+                    continue;
+                };
+
+                match key.as_str() {
+                    // An empty `Disallow` allows everything and adds no rule
+                    _ if value.is_empty() => {}
+                    "allow" => group.allow.push(value.to_owned()),
+                    "disallow" => group.disallow.push(value.to_owned()),
+                    _ => group.crawl_delay = value.parse().ok(),
+                }
+            }
+            "sitemap" => sitemaps.push(value.to_owned()),
+            _ => {}
+        }
+    }
+
+    (groups, sitemaps)
+}
+
+/// Builds the rules applying to `user_agent`: the groups naming it, or the `*` groups.
+fn rules_for(robots_url: String, user_agent: String, robots_txt: &str) -> Robots {
+    let (groups, sitemaps) = parse(robots_txt);
+
+    // Only the product token matters, e.g. `Googlebot` in `Googlebot/2.1`
+    let token = user_agent
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    let specific = groups
+        .iter()
+        .filter(|group| {
+            group
+                .agents
+                .iter()
+                .any(|agent| agent != "*" && token.contains(agent.as_str()))
+        })
+        .collect::<Vec<_>>();
+
+    let matching = if specific.is_empty() {
+        groups
+            .iter()
+            .filter(|group| group.agents.iter().any(|agent| agent == "*"))
+            .collect()
+    } else {
+        specific
+    };
+
+    Robots {
+        robots_url,
+        user_agent,
+        found: true,
+        allow: matching.iter().flat_map(|g| g.allow.clone()).collect(),
+        disallow: matching.iter().flat_map(|g| g.disallow.clone()).collect(),
+        crawl_delay: matching.iter().find_map(|g| g.crawl_delay),
+        sitemaps,
+        allowed: None,
+    }
+}
+
+/// Whether `path` may be crawled under the given rules: the longest matching
+/// rule wins, and `Allow` wins a tie, as specified by RFC 9309.
+fn is_allowed(path: &str, allow: &[String], disallow: &[String]) -> bool {
+    let longest = |rules: &[String]| {
+        rules
+            .iter()
+            .filter(|rule| matches_rule(rule, path))
+            .map(String::len)
+            .max()
+    };
+
+    match (longest(allow), longest(disallow)) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+        (Some(allow), Some(disallow)) => allow >= disallow,
+    }
+}
+
+/// Matches a path against a rule, where `*` matches any run of characters
+/// and a trailing `$` the end of the path.
+fn matches_rule(rule: &str, path: &str) -> bool {
+    let (rule, anchored) = match rule.strip_suffix('$') {
+        Some(rule) => (rule, true),
+        None => (rule, false),
+    };
+
+    let mut parts = rule.split('*');
+
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let parts = parts.collect::<Vec<_>>();
+
+    for (index, part) in parts.iter().enumerate() {
+        // The last part of an anchored rule must end the path
+        if anchored && index == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+
+        match rest.find(part) {
+            Some(start) => rest = &rest[start + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+/// Whether fetching `robots.txt` failed because the site has none.
+fn is_missing(error: &Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        .is_some_and(|status| {
+            status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::GONE
+        })
+}
+
+pub async fn robots(
+    Input {
+        domain,
+        user_agent,
+        path,
+    }: Input,
+) -> Result<Robots, Error> {
+    let user_agent = user_agent.unwrap_or_else(|| "*".to_owned());
+
+    // Accept full URLs as well as bare domains
+    let host = domain
+        .trim()
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .to_owned();
+
+    let robots_url = format!("https://{host}/robots.txt");
+
+    tracing::info!("Fetching robots.txt: {}", robots_url);
+
+    reqwest::Url::parse(&robots_url).map_err(|e| format!("Invalid domain {:?}: {}", domain, e))?;
+
+    let client = crate::tool::fetch::http_client()?;
+
+    let robots_txt = match crate::tool::fetch::fetch_html(&client, &robots_url).await {
+        Ok((_, robots_txt)) => robots_txt,
+        Err(e) if is_missing(&e) => {
+            return Ok(Robots {
+                robots_url,
+                user_agent,
+                found: false,
+                allow: vec![],
+                disallow: vec![],
+                crawl_delay: None,
+                sitemaps: vec![],
+                allowed: path.map(|_| true),
+            });
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut robots = rules_for(robots_url, user_agent, &robots_txt);
+
+    robots.allowed = path.map(|path| is_allowed(&path, &robots.allow, &robots.disallow));
+
+    Ok(robots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn refuses_internal_targets() {
+        for domain in ["127.0.0.1:8081", "http://169.254.169.254/", "localhost"] {
+            let error = robots(Input {
+                domain: domain.to_owned(),
+                user_agent: None,
+                path: None,
+            })
+            .await
+            .unwrap_err();
+
+            assert!(
+                error.is::<crate::ssrf::Blocked>(),
+                "{domain} should be blocked"
+            );
+        }
+    }
+
+    const ROBOTS_TXT: &str = "\
+User-agent: *
+Disallow: /private/
+Allow: /private/public.html
+Crawl-delay: 2
+
+# Googlebot gets its own rules
+User-agent: Googlebot
+User-agent: Googlebot-Image
+Disallow: /search
+Allow: /search/about
+Disallow: /*.pdf$
+
+Sitemap: https://example.com/sitemap.xml
+Sitemap: https://example.com/news-sitemap.xml
+";
+
+    fn rules(user_agent: &str) -> Robots {
+        rules_for(
+            "https://example.com/robots.txt".to_owned(),
+            user_agent.to_owned(),
+            ROBOTS_TXT,
+        )
+    }
+
+    #[test]
+    fn parses_groups_and_sitemaps() {
+        let (groups, sitemaps) = parse(ROBOTS_TXT);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1].agents, ["googlebot", "googlebot-image"]);
+        assert_eq!(groups[0].crawl_delay, Some(2.0));
+        assert_eq!(
+            sitemaps,
+            [
+                "https://example.com/sitemap.xml",
+                "https://example.com/news-sitemap.xml"
+            ]
+        );
+    }
+
+    #[test]
+    fn selects_the_groups_of_the_user_agent() {
+        let googlebot = rules("Googlebot/2.1");
+        assert_eq!(googlebot.disallow, ["/search", "/*.pdf$"]);
+        assert_eq!(googlebot.allow, ["/search/about"]);
+        assert_eq!(googlebot.crawl_delay, None);
+
+        // Agents without a group of their own get the `*` group
+        let other = rules("Bingbot");
+        assert_eq!(other.disallow, ["/private/"]);
+        assert_eq!(other.allow, ["/private/public.html"]);
+        assert_eq!(other.crawl_delay, Some(2.0));
+
+        assert_eq!(rules("*").disallow, ["/private/"]);
+        // Sitemaps apply to every agent
+        assert_eq!(other.sitemaps.len(), 2);
+    }
+
+    #[test]
+    fn applies_the_longest_matching_rule() {
+        let other = rules("Bingbot");
+        let allowed = |path: &str| is_allowed(path, &other.allow, &other.disallow);
+
+        assert!(allowed("/"));
+        assert!(!allowed("/private/notes.html"));
+        assert!(allowed("/private/public.html"));
+
+        let googlebot = rules("Googlebot");
+        let allowed = |path: &str| is_allowed(path, &googlebot.allow, &googlebot.disallow);
+
+        assert!(!allowed("/search?q=rust"));
+        assert!(allowed("/search/about"));
+        assert!(!allowed("/docs/manual.pdf"));
+        assert!(allowed("/docs/manual.pdf?download=1"));
+        assert!(allowed("/private/notes.html"));
+
+        // Allow wins a tie
+        assert!(is_allowed(
+            "/page",
+            &["/page".to_owned()],
+            &["/page".to_owned()]
+        ));
+    }
+
+    #[test]
+    fn matches_wildcards_and_anchors() {
+        assert!(matches_rule("/", "/anything"));
+        assert!(matches_rule("/*/edit", "/wiki/Page/edit"));
+        assert!(matches_rule("/*.php$", "/index.php"));
+        assert!(!matches_rule("/*.php$", "/index.php?page=2"));
+        assert!(matches_rule("/exact$", "/exact"));
+        assert!(!matches_rule("/exact$", "/exactly"));
+        assert!(!matches_rule("/private", "/public/private"));
+    }
+}
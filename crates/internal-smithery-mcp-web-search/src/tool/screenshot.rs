@@ -0,0 +1,130 @@
+use headless_chrome::protocol::cdp::{Emulation, Page};
+use rmcp::schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::tool::fetch::Error;
+
+const DEFAULT_VIEWPORT_WIDTH: u32 = 1280;
+const DEFAULT_VIEWPORT_HEIGHT: u32 = 800;
+
+/// Upper bound of each viewport dimension.
+const MAX_VIEWPORT_SIZE: u32 = 4096;
+
+/// Upper bound of the height of a full-page capture, so that an endless page
+/// doesn't produce an enormous image.
+const MAX_FULL_PAGE_HEIGHT: f64 = 16384.0;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The URL of the page to capture.
+    pub url: String,
+
+    /// If `true`, captures the whole scrollable page instead of the viewport
+    /// (default: `false`).
+    #[serde(default)]
+    pub full_page: bool,
+
+    /// The width of the viewport in CSS pixels (default: 1280, at most 4096).
+    pub viewport_width: Option<u32>,
+
+    /// The height of the viewport in CSS pixels (default: 800, at most 4096).
+    pub viewport_height: Option<u32>,
+}
+
+/// Captures a PNG of a page, returned base64-encoded as given by the browser.
+fn capture(
+    browser: &headless_chrome::Browser,
+    url: &str,
+    full_page: bool,
+    width: u32,
+    height: u32,
+) -> Result<String, Error> {
+    let tab = crate::tool::fetch::new_tab(browser)?;
+
+    let result = (|| -> Result<String, Error> {
+        tab.call_method(Emulation::SetDeviceMetricsOverride {
+            width,
+            height,
+            device_scale_factor: 1.0,
+            mobile: false,
+            scale: None,
+            screen_width: None,
+            screen_height: None,
+            position_x: None,
+            position_y: None,
+            dont_set_visible_size: None,
+            screen_orientation: None,
+            viewport: None,
+            display_feature: None,
+            device_posture: None,
+        })?;
+
+        tab.navigate_to(url)?.wait_until_navigated()?;
+        crate::tool::fetch::check_navigated_url(&tab)?;
+
+        let clip = if full_page {
+            let size = tab
+                .call_method(Page::GetLayoutMetrics(None))?
+                .css_content_size;
+
+            Some(Page::Viewport {
+                x: 0.0,
+                y: 0.0,
+                width: size.width,
+                height: size.height.min(MAX_FULL_PAGE_HEIGHT),
+                scale: 1.0,
+            })
+        } else {
+            None
+        };
+
+        let screenshot = tab.call_method(Page::CaptureScreenshot {
+            format: Some(Page::CaptureScreenshotFormatOption::Png),
+            quality: None,
+            clip,
+            from_surface: Some(true),
+            capture_beyond_viewport: Some(full_page),
+            optimize_for_speed: None,
+        })?;
+
+        Ok(screenshot.data)
+    })();
+
+    let _ = tab.close(false);
+
+    result
+}
+
+/// Captures a screenshot of a page with the shared browser, returning the
+/// base64-encoded PNG.
+pub async fn screenshot(
+    Input {
+        url,
+        full_page,
+        viewport_width,
+        viewport_height,
+    }: Input,
+) -> Result<String, Error> {
+    if crate::config::config().disable_browser {
+        return Err("Screenshots require the browser, which is disabled on this server".into());
+    }
+
+    let target = reqwest::Url::parse(&url).map_err(|e| format!("Invalid URL {:?}: {}", url, e))?;
+    crate::ssrf::check_target(&target).await?;
+
+    let width = viewport_width
+        .unwrap_or(DEFAULT_VIEWPORT_WIDTH)
+        .clamp(1, MAX_VIEWPORT_SIZE);
+    let height = viewport_height
+        .unwrap_or(DEFAULT_VIEWPORT_HEIGHT)
+        .clamp(1, MAX_VIEWPORT_SIZE);
+
+    let _permit = crate::tool::fetch::fetch_permit().await?;
+
+    tracing::info!("Capturing screenshot: {}", url);
+
+    crate::tool::fetch::with_browser(move |browser| {
+        capture(browser, target.as_str(), full_page, width, height)
+    })
+    .await
+}
@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ProviderResult, Query, SearchResult, SearchType};
+use crate::retry::Retryable;
+
+/// The body of Exa's `/search` endpoint, which takes camelCase keys such as
+/// `includeDomains` and `numResults`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_domains: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_domains: Option<Vec<String>>,
+    pub num_results: u64,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub search_type: Option<SearchType>,
+    pub use_autoprompt: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_published_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_published_date: Option<String>,
+    pub contents: Contents,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Contents {
+    pub text: bool,
+    pub summary: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Response {
+    /// Kept raw so that a malformed item doesn't fail the whole response.
+    pub results: Vec<serde_json::Value>,
+}
+
+/// A result as returned by Exa's `/search` endpoint.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExaResult {
+    pub url: String,
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub text: Option<String>,
+    pub author: Option<String>,
+    pub published_date: Option<String>,
+    pub score: Option<f64>,
+    pub favicon: Option<String>,
+}
+
+impl ProviderResult for ExaResult {
+    fn normalize(self) -> SearchResult {
+        SearchResult {
+            title: self.title.unwrap_or_default(),
+            url: self.url,
+            summary: self.summary.unwrap_or_default(),
+            text: self.text.filter(|text| !text.is_empty()),
+            author: self.author.filter(|author| !author.is_empty()),
+            published_date: self.published_date,
+            score: self.score,
+            favicon: self.favicon.filter(|favicon| !favicon.is_empty()),
+        }
+    }
+}
+
+/// Upper bound of the response body quoted in an error.
+const MAX_ERROR_BODY_LENGTH: usize = 500;
+
+/// A failed request to Exa.
+#[derive(Debug)]
+enum ExaError {
+    Request(reqwest::Error),
+    /// Exa answered with a non-success status, e.g. 429 when rate-limited.
+    Status {
+        status: reqwest::StatusCode,
+        body: String,
+    },
+}
+
+impl std::fmt::Display for ExaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "Exa request failed: {}", e),
+            Self::Status { status, body } => {
+                let body: String = body.trim().chars().take(MAX_ERROR_BODY_LENGTH).collect();
+                write!(f, "Exa returned HTTP {}: {}", status, body)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExaError {}
+
+/// Rate limiting and server errors are worth retrying, other client errors
+/// such as an invalid API key are not.
+impl crate::retry::Retryable for ExaError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::Request(e) => e.is_retryable(),
+            Self::Status { status, .. } => {
+                *status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+        }
+    }
+}
+
+impl From<Query> for Request {
+    fn from(
+        Query {
+            query,
+            include_domains,
+            exclude_domains,
+            num_results,
+            include_text,
+            search_type,
+            use_autoprompt,
+            start_published_date,
+            end_published_date,
+        }: Query,
+    ) -> Self {
+        Self {
+            query,
+            include_domains,
+            exclude_domains,
+            num_results,
+            search_type,
+            use_autoprompt,
+            start_published_date,
+            end_published_date,
+            contents: Contents {
+                summary: true,
+                text: include_text,
+            },
+        }
+    }
+}
+
+/// Searches with the Exa API at `api_url`, e.g. `https://api.exa.ai`.
+pub(super) async fn search(
+    api_url: &str,
+    exa_api_key: &str,
+    query: Query,
+) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+    let client = reqwest::Client::new();
+
+    let body = Request::from(query);
+
+    let body_string = serde_json::to_string(&body)?;
+
+    let endpoint = format!("{}/search", api_url);
+
+    let retry = crate::config::config().retry;
+
+    let response = retry
+        .retry(|| async {
+            let response = client
+                .post(&endpoint)
+                .header("x-api-key", exa_api_key)
+                .header("content-type", "application/json")
+                .body(body_string.clone())
+                .send()
+                .await
+                .map_err(ExaError::Request)?;
+
+            crate::metrics::record_exa_rate_limit(response.headers());
+
+            let status = response.status();
+            if status.is_success() {
+                return Ok(response);
+            }
+
+            let body = response.text().await.unwrap_or_default();
+            Err(ExaError::Status { status, body })
+        })
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error> {
+            if e.is_retryable() {
+                format!("{} (gave up after {} attempts)", e, retry.max_attempts).into()
+            } else {
+                e.into()
+            }
+        })?;
+
+    let response = response.text().await?;
+
+    let results = serde_json::from_str::<Response>(&response)?.results;
+
+    Ok(super::normalize_results::<ExaResult>(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use axum::response::IntoResponse;
+
+    use super::*;
+
+    fn query(query: &str) -> Query {
+        Query {
+            query: query.to_owned(),
+            include_domains: None,
+            exclude_domains: None,
+            num_results: 3,
+            include_text: false,
+            search_type: None,
+            use_autoprompt: false,
+            start_published_date: None,
+            end_published_date: None,
+        }
+    }
+
+    fn status(code: u16) -> ExaError {
+        ExaError::Status {
+            status: reqwest::StatusCode::from_u16(code).unwrap(),
+            body: String::new(),
+        }
+    }
+
+    #[test]
+    fn retries_rate_limits_and_server_errors_only() {
+        assert!(status(429).is_retryable());
+        assert!(status(500).is_retryable());
+        assert!(status(503).is_retryable());
+        assert!(!status(400).is_retryable());
+        assert!(!status(401).is_retryable());
+    }
+
+    #[test]
+    fn sends_domain_filters_in_camel_case() {
+        let filtered = Query {
+            query: "rust".to_owned(),
+            include_domains: Some(vec!["example.com".to_owned()]),
+            exclude_domains: Some(vec!["spam.example".to_owned()]),
+            num_results: 5,
+            include_text: true,
+            search_type: Some(SearchType::Keyword),
+            use_autoprompt: false,
+            start_published_date: Some("2024-01-01".to_owned()),
+            end_published_date: None,
+        };
+
+        let body = serde_json::to_value(Request::from(filtered)).unwrap();
+
+        assert_eq!(body["includeDomains"], serde_json::json!(["example.com"]));
+        assert_eq!(body["excludeDomains"], serde_json::json!(["spam.example"]));
+        assert_eq!(body["numResults"], 5);
+        assert_eq!(body["type"], "keyword");
+        assert_eq!(body["useAutoprompt"], false);
+        assert_eq!(body["startPublishedDate"], "2024-01-01");
+        assert_eq!(
+            body["contents"],
+            serde_json::json!({ "text": true, "summary": true })
+        );
+        assert!(body.get("include_domains").is_none());
+        assert!(body.get("endPublishedDate").is_none());
+
+        let body = serde_json::to_value(Request::from(query("rust"))).unwrap();
+
+        assert!(body.get("includeDomains").is_none());
+        assert!(body.get("excludeDomains").is_none());
+        assert!(body.get("type").is_none());
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited_searches() {
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let counter = requests.clone();
+        let router = axum::Router::new().route(
+            "/search",
+            axum::routing::post(move || {
+                let attempt = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt < 2 {
+                        return (reqwest::StatusCode::TOO_MANY_REQUESTS, "slow down")
+                            .into_response();
+                    }
+                    axum::Json(serde_json::json!({
+                        "results": [{ "url": "https://example.com/" }],
+                    }))
+                    .into_response()
+                }
+            }),
+        );
+        let api_url = crate::test_support::serve(router).await;
+
+        let results = search(&api_url, "key", query("rust")).await.unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].url, "https://example.com/");
+    }
+
+    #[test]
+    fn reads_page_texts() {
+        let results = super::super::normalize_results::<ExaResult>(vec![
+            serde_json::json!({
+                "url": "https://example.com/a",
+                "title": "A",
+                "summary": "About A",
+                "text": "The full text of A.",
+            }),
+            serde_json::json!({ "url": "https://example.com/b", "text": "" }),
+            serde_json::json!({ "url": "https://example.com/c" }),
+        ]);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].text.as_deref(), Some("The full text of A."));
+        assert_eq!(results[1].text, None);
+        assert_eq!(results[2].text, None);
+
+        let json = serde_json::to_value(&results[1]).unwrap();
+        assert!(json.get("text").is_none());
+    }
+}
@@ -0,0 +1,142 @@
+//! Query expansion with a simple synonym list, for recall-sensitive searches.
+
+/// Used when `SEARCH_SYNONYMS_FILE` is not set.
+const DEFAULT_SYNONYMS: &[(&str, &[&str])] = &[
+    ("bug", &["issue", "defect"]),
+    ("error", &["exception", "failure"]),
+    ("fast", &["performance", "speed"]),
+    ("slow", &["performance", "latency"]),
+    ("guide", &["tutorial", "how-to"]),
+    ("tutorial", &["guide", "walkthrough"]),
+    ("docs", &["documentation", "reference"]),
+    ("documentation", &["docs", "reference"]),
+    ("example", &["sample", "demo"]),
+    ("install", &["setup", "installation"]),
+    ("setup", &["install", "configuration"]),
+    ("config", &["configuration", "settings"]),
+    ("comparison", &["versus", "alternatives"]),
+    ("vs", &["comparison", "versus"]),
+    ("price", &["pricing", "cost"]),
+    ("security", &["vulnerability", "hardening"]),
+];
+
+/// Appends the synonyms of the terms found in `query`, skipping those it
+/// already contains. Returns `None` when there is nothing to add.
+pub(super) fn expand_query(query: &str, synonyms: &[(String, Vec<String>)]) -> Option<String> {
+    let words = words(query);
+
+    // Whether the query contains a term, which may span several words
+    let contains = |term: &str| {
+        let term = self::words(term);
+        !term.is_empty() && words.windows(term.len()).any(|window| window == term)
+    };
+
+    let entries: Vec<(&str, Vec<&str>)> = if synonyms.is_empty() {
+        DEFAULT_SYNONYMS
+            .iter()
+            .map(|&(term, related)| (term, related.to_vec()))
+            .collect()
+    } else {
+        synonyms
+            .iter()
+            .map(|(term, related)| (term.as_str(), related.iter().map(String::as_str).collect()))
+            .collect()
+    };
+
+    let mut additions: Vec<&str> = vec![];
+
+    for (term, related) in entries {
+        if !contains(term) {
+            continue;
+        }
+
+        for synonym in related {
+            let known = additions.iter().any(|a| a.eq_ignore_ascii_case(synonym));
+
+            if !known && !contains(synonym) {
+                additions.push(synonym);
+            }
+        }
+    }
+
+    (!additions.is_empty()).then(|| format!("{} {}", query.trim(), additions.join(" ")))
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '-')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synonyms(entries: &[(&str, &[&str])]) -> Vec<(String, Vec<String>)> {
+        entries
+            .iter()
+            .map(|&(term, related)| {
+                (
+                    term.to_owned(),
+                    related.iter().map(|&s| s.to_owned()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn appends_the_synonyms_of_each_term() {
+        assert_eq!(
+            expand_query(" rust install guide ", &[]).as_deref(),
+            Some("rust install guide tutorial how-to setup installation")
+        );
+    }
+
+    #[test]
+    fn skips_synonyms_already_in_the_query() {
+        assert_eq!(
+            expand_query("docs Reference", &[]).as_deref(),
+            Some("docs Reference documentation")
+        );
+    }
+
+    #[test]
+    fn adds_each_synonym_once() {
+        // Both terms list `performance`
+        assert_eq!(
+            expand_query("fast or slow", &[]).as_deref(),
+            Some("fast or slow performance speed latency")
+        );
+    }
+
+    #[test]
+    fn matches_multi_word_terms() {
+        let custom = synonyms(&[("machine learning", &["ml", "deep learning"])]);
+
+        assert_eq!(
+            expand_query("intro to Machine Learning", &custom).as_deref(),
+            Some("intro to Machine Learning ml deep learning")
+        );
+        // Every word of the term must appear, in order
+        assert_eq!(expand_query("machine tools", &custom), None);
+        assert_eq!(expand_query("learning machine", &custom), None);
+    }
+
+    #[test]
+    fn custom_synonyms_replace_the_defaults() {
+        let custom = synonyms(&[("k8s", &["kubernetes"])]);
+
+        assert_eq!(
+            expand_query("k8s bug", &custom).as_deref(),
+            Some("k8s bug kubernetes")
+        );
+        assert_eq!(expand_query("bug report", &custom), None);
+    }
+
+    #[test]
+    fn returns_none_when_nothing_is_added() {
+        assert_eq!(expand_query("rust lifetimes", &[]), None);
+        assert_eq!(expand_query("", &[]), None);
+    }
+}
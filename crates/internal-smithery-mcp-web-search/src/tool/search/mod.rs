@@ -0,0 +1,689 @@
+mod exa;
+mod expand;
+
+use std::sync::LazyLock;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::cache::TtlCache;
+
+/// Provider results keyed by the normalized query and filters, since every
+/// search is charged against the provider's quota.
+static CACHE: LazyLock<TtlCache<Query, Vec<SearchResult>>> = LazyLock::new(TtlCache::new);
+
+/// A search as sent to the provider.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Query {
+    query: String,
+    include_domains: Option<Vec<String>>,
+    exclude_domains: Option<Vec<String>>,
+    num_results: u64,
+    include_text: bool,
+    search_type: Option<SearchType>,
+    use_autoprompt: bool,
+    start_published_date: Option<String>,
+    end_published_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The natural language query to search for.
+    pub query: String,
+
+    /// If specified, results will only come from these domains.
+    /// e.g., `["example.com"]`
+    /// Domains are normalized: lowercased, without `www.`, and reduced to
+    /// the host when a full URL is given (`https://WWW.Example.com/path` becomes `example.com`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_domains: Option<Vec<String>>,
+
+    /// If specified, results will never come from these domains,
+    /// e.g., `["example.com"]`. Normalized like `include_domains`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_domains: Option<Vec<String>>,
+
+    /// The order of the results: `input` or `relevance` (the search engine's ranking),
+    /// `alphabetical` (by title) or `shortest_first` (by summary length).
+    pub order: Option<crate::order::Order>,
+
+    /// If `true`, each result includes the URL of its site's favicon,
+    /// e.g. for displaying the results. The favicon itself is not fetched.
+    pub include_favicon: Option<bool>,
+
+    /// If `true`, bypasses the result cache and always queries the search
+    /// engine, e.g. for breaking news (default: `false`).
+    pub no_cache: Option<bool>,
+
+    /// If `true`, the query is augmented with related terms before searching,
+    /// for a better recall on research queries (default: `false`).
+    /// The expanded query is reported with the results.
+    pub expand_query: Option<bool>,
+
+    /// The number of results to return (default: 3). Values outside 1 to 25
+    /// are clamped.
+    pub num_results: Option<u64>,
+
+    /// If `true`, each result includes the full text of its page as extracted
+    /// by the search engine, e.g. for retrieval-augmented generation
+    /// (default: `false`). Summaries are included either way.
+    pub include_text: Option<bool>,
+
+    /// How each result is written: `json` (default) or `citation`, a
+    /// markdown line `[title](url): summary` ready to quote.
+    pub format: Option<Format>,
+
+    /// The search mode: `neural` works better for conceptual queries, e.g.
+    /// "articles explaining borrow checking", `keyword` for exact phrases,
+    /// names or error messages (default: chosen by the search engine).
+    pub search_type: Option<SearchType>,
+
+    /// If `true`, the search engine rewrites the query to improve neural
+    /// retrieval, e.g. for open-ended research. Leave it off to look up
+    /// exact documents (default: `false`).
+    pub use_autoprompt: Option<bool>,
+
+    /// If specified, only results published on or after this date, as an
+    /// ISO 8601 date (`2024-01-31`) or date-time (`2024-01-31T09:00:00Z`).
+    pub start_published_date: Option<String>,
+
+    /// If specified, only results published on or before this date, in the
+    /// same format as `start_published_date`.
+    pub end_published_date: Option<String>,
+}
+
+impl Input {
+    /// Checks the published date range, so that a malformed date is reported
+    /// as an invalid parameter rather than sent to the search engine.
+    pub fn validate_published_dates(&self) -> Result<(), String> {
+        let start = parse_published_date("start_published_date", &self.start_published_date)?;
+        let end = parse_published_date("end_published_date", &self.end_published_date)?;
+
+        if let (Some(start), Some(end)) = (start, end)
+            && start > end
+        {
+            return Err(format!(
+                "start_published_date ({}) is after end_published_date ({})",
+                start, end
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses an ISO 8601 date or RFC 3339 date-time, reduced to its date.
+fn parse_published_date(
+    name: &str,
+    date: &Option<String>,
+) -> Result<Option<chrono::NaiveDate>, String> {
+    let Some(date) = date.as_deref().map(str::trim) else {
+        return Ok(None);
+    };
+
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .or_else(|_| chrono::DateTime::parse_from_rfc3339(date).map(|date| date.date_naive()))
+        .map(Some)
+        .map_err(|_| {
+            format!(
+                "Invalid {} {:?}: expected an ISO 8601 date such as 2024-01-31",
+                name, date
+            )
+        })
+}
+
+/// The search modes of the search engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchType {
+    /// Embedding-based search, matching the meaning of the query.
+    Neural,
+    /// Traditional search, matching the words of the query.
+    Keyword,
+}
+
+/// The layout of each search result in the tool response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// The result as a JSON object.
+    #[default]
+    Json,
+    /// A markdown citation, `[title](url): summary`.
+    Citation,
+}
+
+const DEFAULT_NUM_RESULTS: u64 = 3;
+const MAX_NUM_RESULTS: u64 = 25;
+
+/// The number of results to request, given the one asked for, if any.
+fn clamp_num_results(num_results: Option<u64>) -> u64 {
+    num_results
+        .unwrap_or(DEFAULT_NUM_RESULTS)
+        .clamp(1, MAX_NUM_RESULTS)
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+    /// The query actually sent, when `expand_query` changed it.
+    pub expanded_query: Option<String>,
+}
+
+/// A search result, in the same shape whichever provider produced it.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub summary: String,
+    /// The full text of the page, when `include_text` is requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// The publication date, as reported by the provider (usually ISO 8601).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_date: Option<String>,
+    /// The provider's relevance score. Scales differ between providers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// The favicon URL, when `include_favicon` is requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+}
+
+impl SearchResult {
+    /// Formats the result as a markdown citation, `[title](url): summary`.
+    pub fn citation(&self) -> String {
+        let title = if self.title.trim().is_empty() {
+            &self.url
+        } else {
+            self.title.trim()
+        };
+        let title = title.replace('[', "\\[").replace(']', "\\]");
+
+        let summary = self
+            .summary
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if summary.is_empty() {
+            format!("[{}]({})", title, self.url)
+        } else {
+            format!("[{}]({}): {}", title, self.url, summary)
+        }
+    }
+}
+
+/// A result in a provider's own response shape.
+trait ProviderResult: DeserializeOwned {
+    /// Maps the result to the canonical [`SearchResult`].
+    fn normalize(self) -> SearchResult;
+}
+
+/// Normalizes the raw results of a provider, skipping the malformed ones
+/// rather than failing the whole response.
+fn normalize_results<R: ProviderResult>(results: Vec<serde_json::Value>) -> Vec<SearchResult> {
+    results
+        .into_iter()
+        .filter_map(|result| match serde_json::from_value::<R>(result) {
+            Ok(result) => Some(result.normalize()),
+            Err(e) => {
+                tracing::warn!("Skipping malformed search result: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+impl crate::order::Ordered for SearchResult {
+    fn label(&self) -> &str {
+        &self.title
+    }
+
+    fn content_length(&self) -> usize {
+        self.summary.chars().count()
+    }
+}
+
+/// Reduces a domain, or a full URL, to its lowercase host without `www.`.
+fn normalize_domain(domain: &str) -> String {
+    let domain = domain.trim().to_lowercase();
+
+    let without_scheme = domain
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(&domain);
+
+    let host = without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+
+    let host = host.rsplit_once('@').map(|(_, host)| host).unwrap_or(host);
+    let host = host.split(':').next().unwrap_or_default();
+    let host = host.trim_end_matches('.');
+
+    host.strip_prefix("www.").unwrap_or(host).to_owned()
+}
+
+/// The conventional favicon location of the site serving `url`.
+fn favicon_url(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url).ok()?;
+
+    let host = url.host_str()?;
+
+    matches!(url.scheme(), "http" | "https")
+        .then(|| format!("{}://{}/favicon.ico", url.scheme(), host))
+}
+
+/// Normalizes, sorts and deduplicates domains.
+fn normalize_domains(domains: Vec<String>) -> Vec<String> {
+    let mut domains: Vec<String> = domains
+        .iter()
+        .map(|domain| normalize_domain(domain))
+        .filter(|domain| !domain.is_empty())
+        .collect();
+
+    domains.sort();
+    domains.dedup();
+    domains
+}
+
+/// Records a search by the source of its results.
+fn record_search(source: &str, results: usize) {
+    let labels = [("tool", "search"), ("source", source)];
+
+    crate::metrics::increment("search_requests_total", &labels);
+    crate::metrics::add("search_results_total", &labels, results as f64);
+}
+
+const MISSING_API_KEY: &str = "Search is not configured: no Exa API key. \
+Set EXA_API_KEY on the server, or pass `exa_api_key` in the query string of the MCP URL. \
+Get a key at https://dashboard.exa.ai/api-keys";
+
+pub async fn search(
+    exa_api_key: String,
+    input: Input,
+) -> Result<SearchResponse, Box<dyn std::error::Error>> {
+    search_at(&crate::config::config().exa_api_url, exa_api_key, input).await
+}
+
+/// Searches with the Exa API at `api_url`.
+async fn search_at(
+    api_url: &str,
+    exa_api_key: String,
+    Input {
+        query,
+        include_domains,
+        exclude_domains,
+        order,
+        include_favicon,
+        no_cache,
+        expand_query,
+        num_results,
+        include_text,
+        format: _,
+        search_type,
+        use_autoprompt,
+        start_published_date,
+        end_published_date,
+    }: Input,
+) -> Result<SearchResponse, Box<dyn std::error::Error>> {
+    if exa_api_key.trim().is_empty() {
+        return Err(MISSING_API_KEY.into());
+    }
+
+    let num_results = clamp_num_results(num_results);
+
+    let expanded_query = expand_query
+        .unwrap_or(false)
+        .then(|| expand::expand_query(&query, &crate::config::config().search_synonyms))
+        .flatten();
+
+    let query = expanded_query.clone().unwrap_or(query);
+
+    let query = Query {
+        query,
+        include_domains: include_domains.map(normalize_domains),
+        exclude_domains: exclude_domains.map(normalize_domains),
+        num_results,
+        include_text: include_text.unwrap_or(false),
+        search_type,
+        use_autoprompt: use_autoprompt.unwrap_or(false),
+        start_published_date: start_published_date.map(|date| date.trim().to_owned()),
+        end_published_date: end_published_date.map(|date| date.trim().to_owned()),
+    };
+
+    let cache_key = Query {
+        query: query
+            .query
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase(),
+        ..query.clone()
+    };
+
+    let cached = if no_cache.unwrap_or(false) {
+        None
+    } else {
+        CACHE.get(&cache_key)
+    };
+
+    let mut results = match cached {
+        Some(results) => {
+            tracing::info!("Search cache hit: {}", query.query);
+            record_search("cache", results.len());
+            results
+        }
+        None => {
+            let results = exa::search(api_url, &exa_api_key, query).await?;
+            record_search("exa", results.len());
+            CACHE.insert(
+                cache_key,
+                results.clone(),
+                crate::config::config().search_cache_ttl,
+            );
+            results
+        }
+    };
+
+    for result in &mut results {
+        if include_favicon.unwrap_or(false) {
+            // The provider's favicon is more accurate when it knows one
+            if result.favicon.is_none() {
+                result.favicon = favicon_url(&result.url);
+            }
+        } else {
+            result.favicon = None;
+        }
+    }
+
+    order.unwrap_or_default().apply(&mut results);
+
+    Ok(SearchResponse {
+        results,
+        expanded_query,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    /// A mock of Exa's `/search` endpoint answering `body`, and the number of
+    /// requests it received.
+    async fn mock_exa(body: serde_json::Value) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let counter = requests.clone();
+        let router = axum::Router::new().route(
+            "/search",
+            axum::routing::post(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let body = body.clone();
+                async move { axum::Json(body) }
+            }),
+        );
+
+        (crate::test_support::serve(router).await, requests)
+    }
+
+    fn input(query: &str) -> Input {
+        serde_json::from_value(serde_json::json!({ "query": query })).unwrap()
+    }
+
+    #[test]
+    fn normalizes_domain_filters() {
+        let domains = normalize_domains(vec![
+            "https://WWW.Example.com/path".to_owned(),
+            "example.com".to_owned(),
+            "docs.rs".to_owned(),
+            " ".to_owned(),
+        ]);
+
+        assert_eq!(domains, ["docs.rs", "example.com"]);
+    }
+
+    #[test]
+    fn accepts_any_result_count() {
+        let input: Input = serde_json::from_value(serde_json::json!({
+            "query": "rust",
+            "num_results": 1000,
+        }))
+        .unwrap();
+
+        assert_eq!(clamp_num_results(input.num_results), MAX_NUM_RESULTS);
+        assert_eq!(clamp_num_results(Some(0)), 1);
+        assert_eq!(clamp_num_results(Some(10)), 10);
+        assert_eq!(clamp_num_results(None), DEFAULT_NUM_RESULTS);
+    }
+
+    #[derive(Deserialize)]
+    struct MockResult {
+        link: String,
+    }
+
+    impl ProviderResult for MockResult {
+        fn normalize(self) -> SearchResult {
+            SearchResult {
+                title: String::new(),
+                url: self.link,
+                summary: String::new(),
+                text: None,
+                author: None,
+                published_date: None,
+                score: None,
+                favicon: None,
+            }
+        }
+    }
+
+    #[test]
+    fn skips_malformed_results() {
+        let results = normalize_results::<MockResult>(vec![
+            serde_json::json!({ "link": "https://example.com/a" }),
+            serde_json::json!({ "link": 42 }),
+            serde_json::json!("not an object"),
+            serde_json::json!({ "link": "https://example.com/b" }),
+        ]);
+
+        let urls: Vec<_> = results.iter().map(|result| result.url.as_str()).collect();
+        assert_eq!(urls, ["https://example.com/a", "https://example.com/b"]);
+    }
+
+    /// A provider nesting its metadata, unlike Exa's flat results.
+    #[derive(Deserialize)]
+    struct NestedResult {
+        link: String,
+        name: String,
+        snippet: String,
+        meta: NestedMeta,
+        relevance: f64,
+    }
+
+    #[derive(Deserialize)]
+    struct NestedMeta {
+        byline: Option<String>,
+        date: Option<String>,
+    }
+
+    impl ProviderResult for NestedResult {
+        fn normalize(self) -> SearchResult {
+            SearchResult {
+                title: self.name,
+                url: self.link,
+                summary: self.snippet,
+                text: None,
+                author: self.meta.byline,
+                published_date: self.meta.date,
+                score: Some(self.relevance),
+                favicon: None,
+            }
+        }
+    }
+
+    /// A provider with flat, camelCase results.
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct FlatResult {
+        url: String,
+        title: String,
+        description: String,
+        author_name: Option<String>,
+        published_at: Option<String>,
+        score: f64,
+    }
+
+    impl ProviderResult for FlatResult {
+        fn normalize(self) -> SearchResult {
+            SearchResult {
+                title: self.title,
+                url: self.url,
+                summary: self.description,
+                text: None,
+                author: self.author_name,
+                published_date: self.published_at,
+                score: Some(self.score),
+                favicon: None,
+            }
+        }
+    }
+
+    #[test]
+    fn normalizes_every_provider_alike() {
+        let nested = normalize_results::<NestedResult>(vec![serde_json::json!({
+            "link": "https://example.com/post",
+            "name": "A post",
+            "snippet": "What the post is about.",
+            "meta": { "byline": "Alice", "date": "2024-01-31" },
+            "relevance": 0.5,
+        })]);
+        let flat = normalize_results::<FlatResult>(vec![serde_json::json!({
+            "url": "https://example.com/post",
+            "title": "A post",
+            "description": "What the post is about.",
+            "authorName": "Alice",
+            "publishedAt": "2024-01-31",
+            "score": 0.5,
+        })]);
+
+        assert_eq!(
+            serde_json::to_value(&nested).unwrap(),
+            serde_json::to_value(&flat).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&nested).unwrap(),
+            serde_json::json!([{
+                "title": "A post",
+                "url": "https://example.com/post",
+                "summary": "What the post is about.",
+                "author": "Alice",
+                "published_date": "2024-01-31",
+                "score": 0.5,
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn names_the_missing_api_key() {
+        for key in ["", "  "] {
+            let error = search_at("http://127.0.0.1:9", key.to_owned(), input("rust"))
+                .await
+                .unwrap_err()
+                .to_string();
+
+            assert!(error.contains("EXA_API_KEY"), "{error}");
+            assert!(error.contains("exa_api_key"), "{error}");
+        }
+    }
+
+    #[tokio::test]
+    async fn answers_repeated_searches_from_the_cache() {
+        let (api_url, requests) = mock_exa(serde_json::json!({
+            "results": [{ "url": "https://example.com/", "title": "Example" }],
+        }))
+        .await;
+
+        let first = search_at(&api_url, "key".to_owned(), input("cache hit test"))
+            .await
+            .unwrap();
+        // Same query up to case and whitespace
+        let second = search_at(&api_url, "key".to_owned(), input(" Cache  HIT test "))
+            .await
+            .unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert_eq!(second.results.len(), 1);
+        assert_eq!(second.results[0].url, first.results[0].url);
+
+        let mut input = input("cache hit test");
+        input.no_cache = Some(true);
+        search_at(&api_url, "key".to_owned(), input).await.unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn derives_favicons_from_the_host() {
+        assert_eq!(
+            favicon_url("https://docs.rs/serde/latest/serde/?search=x").as_deref(),
+            Some("https://docs.rs/favicon.ico")
+        );
+        assert_eq!(
+            favicon_url("http://user@Example.COM/a").as_deref(),
+            Some("http://example.com/favicon.ico")
+        );
+        assert_eq!(favicon_url("ftp://example.com/file"), None);
+        assert_eq!(favicon_url("not a url"), None);
+    }
+
+    #[tokio::test]
+    async fn includes_favicons_on_request() {
+        let (api_url, _) = mock_exa(serde_json::json!({
+            "results": [
+                { "url": "https://example.com/a" },
+                { "url": "https://example.org/b", "favicon": "https://cdn.example.org/icon.png" },
+            ],
+        }))
+        .await;
+
+        let mut with_favicon = input("favicon test");
+        with_favicon.include_favicon = Some(true);
+        let response = search_at(&api_url, "key".to_owned(), with_favicon)
+            .await
+            .unwrap();
+
+        let favicons: Vec<_> = response
+            .results
+            .iter()
+            .map(|result| result.favicon.as_deref())
+            .collect();
+        assert_eq!(
+            favicons,
+            [
+                Some("https://example.com/favicon.ico"),
+                Some("https://cdn.example.org/icon.png"),
+            ]
+        );
+
+        // Served from the cache, which must not keep the favicons either way
+        let response = search_at(&api_url, "key".to_owned(), input("favicon test"))
+            .await
+            .unwrap();
+
+        assert!(
+            response
+                .results
+                .iter()
+                .all(|result| result.favicon.is_none())
+        );
+    }
+}
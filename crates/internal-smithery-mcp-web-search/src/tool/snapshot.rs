@@ -0,0 +1,199 @@
+use rmcp::schemars::JsonSchema;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::tool::{
+    fetch::{Error, Language},
+    metadata::Metadata,
+};
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The URL of the page to snapshot.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    pub url: String,
+    pub title: Option<String>,
+    pub markdown: String,
+    pub links: Vec<Link>,
+    pub images: Vec<Image>,
+    pub metadata: Metadata,
+    pub language: Option<Language>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Link {
+    pub url: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Image {
+    pub url: String,
+    pub alt: Option<String>,
+}
+
+/// Extracts the links and images of a document, resolved against `base`.
+fn extract_links_and_images(base: &reqwest::Url, html: &str) -> (Vec<Link>, Vec<Image>) {
+    let document = Html::parse_document(html);
+
+    let link_selector = Selector::parse("a[href]").unwrap();
+    let image_selector = Selector::parse("img[src]").unwrap();
+
+    let links = document
+        .select(&link_selector)
+        .filter_map(|a| {
+            let url = base.join(a.value().attr("href")?).ok()?;
+            matches!(url.scheme(), "http" | "https").then(|| Link {
+                url: url.to_string(),
+                text: a.text().collect::<Vec<_>>().join(" ").trim().to_owned(),
+            })
+        })
+        .collect();
+
+    let images = document
+        .select(&image_selector)
+        .filter_map(|img| {
+            let url = base.join(img.value().attr("src")?).ok()?;
+            Some(Image {
+                url: url.to_string(),
+                alt: img
+                    .value()
+                    .attr("alt")
+                    .map(str::trim)
+                    .filter(|alt| !alt.is_empty())
+                    .map(str::to_owned),
+            })
+        })
+        .collect();
+
+    (links, images)
+}
+
+pub async fn snapshot(Input { url }: Input) -> Result<Snapshot, Error> {
+    let client = crate::tool::fetch::http_client()?;
+
+    let (final_url, html) = crate::tool::fetch::fetch_html(&client, &url).await?;
+
+    let snapshot = crate::tool::fetch::offload(move || {
+        let markdown = crate::tool::fetch::process_html(&html);
+        // Relative URLs of the page resolve against where it was served from
+        let metadata = crate::tool::metadata::extract_metadata(final_url.as_str(), &html);
+        let (links, images) = extract_links_and_images(&final_url, &html);
+        let language =
+            crate::tool::fetch::detect_language(&crate::tool::fetch::markdown_to_text(&markdown));
+
+        Snapshot {
+            url,
+            title: metadata.title.clone(),
+            markdown,
+            links,
+            images,
+            metadata,
+            language,
+        }
+    })
+    .await?;
+
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn resolves_against_the_final_url() {
+        let html = r#"<html><head><meta property="og:image" content="cover.png"></head>
+            <body><a href="next.html">Next</a><img src="figure.png"></body></html>"#;
+
+        let base = crate::test_support::serve(
+            axum::Router::new()
+                .route(
+                    "/old",
+                    axum::routing::get(|| async { axum::response::Redirect::to("/docs/page") }),
+                )
+                .route(
+                    "/docs/page",
+                    axum::routing::get(move || async move { axum::response::Html(html) }),
+                ),
+        )
+        .await;
+
+        let snapshot = snapshot(Input {
+            url: format!("{base}/old"),
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(snapshot.url, format!("{base}/old"));
+        assert_eq!(
+            snapshot.metadata.og_image,
+            Some(format!("{base}/docs/cover.png"))
+        );
+        assert_eq!(snapshot.links[0].url, format!("{base}/docs/next.html"));
+        assert_eq!(snapshot.images[0].url, format!("{base}/docs/figure.png"));
+    }
+
+    #[tokio::test]
+    async fn populates_every_section() {
+        let html = r#"<!doctype html>
+            <html lang="en"><head>
+                <title>Baking Bread at Home</title>
+                <meta name="description" content="A beginner's guide to baking bread.">
+                <meta name="author" content="Jane Doe">
+                <meta property="og:image" content="/img/loaf.jpg">
+            </head><body>
+                <h1>Baking Bread at Home</h1>
+                <p>Baking bread at home takes flour, water, salt and yeast, and a little patience.</p>
+                <p>Mix the ingredients, knead the dough for ten minutes, then let it rise for an hour.</p>
+                <img src="/img/dough.jpg" alt="Kneaded dough">
+                <a href="/recipes/sourdough">Sourdough recipe</a>
+                <a href="mailto:jane@example.com">Write to me</a>
+            </body></html>"#;
+
+        let base = crate::test_support::serve(axum::Router::new().route(
+            "/bread",
+            axum::routing::get(move || async move { axum::response::Html(html) }),
+        ))
+        .await;
+
+        let snapshot = snapshot(Input {
+            url: format!("{base}/bread"),
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(snapshot.title.as_deref(), Some("Baking Bread at Home"));
+        assert!(
+            snapshot.markdown.contains("# Baking Bread at Home"),
+            "{}",
+            snapshot.markdown
+        );
+        assert!(snapshot.markdown.contains("knead the dough"));
+
+        assert_eq!(
+            snapshot.metadata.description.as_deref(),
+            Some("A beginner's guide to baking bread.")
+        );
+        assert_eq!(snapshot.metadata.author.as_deref(), Some("Jane Doe"));
+        assert_eq!(
+            snapshot.metadata.og_image,
+            Some(format!("{base}/img/loaf.jpg"))
+        );
+
+        // Only web links are kept
+        assert_eq!(snapshot.links.len(), 1);
+        assert_eq!(snapshot.links[0].url, format!("{base}/recipes/sourdough"));
+        assert_eq!(snapshot.links[0].text, "Sourdough recipe");
+
+        assert_eq!(snapshot.images.len(), 1);
+        assert_eq!(snapshot.images[0].url, format!("{base}/img/dough.jpg"));
+        assert_eq!(snapshot.images[0].alt.as_deref(), Some("Kneaded dough"));
+
+        assert_eq!(snapshot.language.unwrap().code, "eng");
+    }
+}
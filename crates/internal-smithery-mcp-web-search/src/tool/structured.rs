@@ -0,0 +1,332 @@
+//! Extracts schema.org items embedded in pages as JSON-LD, microdata or RDFa,
+//! which are far cleaner than the scraped text of e.g. product or recipe pages.
+
+use rmcp::schemars::JsonSchema;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::tool::fetch::Error;
+
+/// The schema.org types returned, matched on the type name without its vocabulary.
+const RECOGNIZED_TYPES: &[&str] = &[
+    "Product",
+    "Recipe",
+    "Article",
+    "NewsArticle",
+    "BlogPosting",
+    "TechArticle",
+    "ScholarlyArticle",
+];
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Input {
+    /// The URL of the page whose structured data to extract.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Item {
+    /// `json-ld`, `microdata` or `rdfa`.
+    pub source: &'static str,
+    /// The schema.org type, e.g. `Product`.
+    #[serde(rename = "type")]
+    pub item_type: String,
+    /// The properties of the item. Nested items are objects with an `@type`.
+    pub properties: Map<String, Value>,
+}
+
+/// The attributes marking up items in microdata or RDFa.
+struct Syntax {
+    source: &'static str,
+    scope: &'static str,
+    item_type: &'static str,
+    property: &'static str,
+}
+
+const MICRODATA: Syntax = Syntax {
+    source: "microdata",
+    scope: "itemscope",
+    item_type: "itemtype",
+    property: "itemprop",
+};
+
+const RDFA: Syntax = Syntax {
+    source: "rdfa",
+    scope: "typeof",
+    item_type: "typeof",
+    property: "property",
+};
+
+/// Strips the vocabulary from a type or property, e.g. `https://schema.org/Product`
+/// or `schema:Product` to `Product`.
+fn local_name(name: &str) -> &str {
+    name.trim_end_matches('/')
+        .rsplit(['/', ':', '#'])
+        .next()
+        .unwrap_or(name)
+}
+
+fn is_recognized(item_type: &str) -> bool {
+    RECOGNIZED_TYPES.contains(&item_type)
+}
+
+/// Adds a property, turning it into an array when it is repeated.
+fn insert(properties: &mut Map<String, Value>, name: &str, value: Value) {
+    match properties.get_mut(name) {
+        Some(Value::Array(values)) => values.push(value),
+        Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+        None => {
+            properties.insert(name.to_owned(), value);
+        }
+    }
+}
+
+fn text_of(element: ElementRef) -> String {
+    element
+        .text()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The types of an item, by local name.
+fn types_of(element: ElementRef, syntax: &Syntax) -> Vec<String> {
+    element
+        .value()
+        .attr(syntax.item_type)
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|item_type| local_name(item_type).to_owned())
+        .collect()
+}
+
+/// The value of a property that is not itself an item.
+fn property_value(base: &reqwest::Url, element: ElementRef, syntax: &Syntax) -> Value {
+    let value = element.value();
+    let resolve = |url: &str| base.join(url).map_or(url.to_owned(), |url| url.to_string());
+
+    // In RDFa, `content` overrides the value of any element
+    if syntax.source == "rdfa"
+        && let Some(content) = value.attr("content")
+    {
+        return Value::String(content.to_owned());
+    }
+
+    let found = match value.name() {
+        "meta" => value.attr("content").map(str::to_owned),
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => {
+            value.attr("src").map(resolve)
+        }
+        "a" | "area" | "link" => value.attr("href").map(resolve),
+        "object" => value.attr("data").map(resolve),
+        "data" | "meter" => value.attr("value").map(str::to_owned),
+        "time" => value.attr("datetime").map(str::to_owned),
+        _ => None,
+    };
+
+    Value::String(found.unwrap_or_else(|| text_of(element)))
+}
+
+fn item_object(base: &reqwest::Url, element: ElementRef, syntax: &Syntax) -> Map<String, Value> {
+    let mut properties = Map::new();
+
+    let types = types_of(element, syntax);
+    if !types.is_empty() {
+        properties.insert("@type".to_owned(), Value::String(types.join(" ")));
+    }
+
+    collect_properties(base, element, syntax, &mut properties);
+
+    properties
+}
+
+/// Collects the properties of the item rooted at `element`, without
+/// descending into nested items.
+fn collect_properties(
+    base: &reqwest::Url,
+    element: ElementRef,
+    syntax: &Syntax,
+    properties: &mut Map<String, Value>,
+) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        let value = child.value();
+        let is_item = value.attr(syntax.scope).is_some();
+
+        if let Some(names) = value.attr(syntax.property) {
+            let property = if is_item {
+                Value::Object(item_object(base, child, syntax))
+            } else {
+                property_value(base, child, syntax)
+            };
+
+            for name in names.split_whitespace() {
+                insert(properties, local_name(name), property.clone());
+            }
+        }
+
+        if !is_item {
+            collect_properties(base, child, syntax, properties);
+        }
+    }
+}
+
+/// Extracts the top-level items of a syntax, i.e. those that are not the
+/// property of another item.
+fn extract_items(base: &reqwest::Url, document: &Html, syntax: &Syntax) -> Vec<Item> {
+    let selector = Selector::parse(&format!("[{}]", syntax.scope)).unwrap();
+
+    document
+        .select(&selector)
+        .filter(|element| element.value().attr(syntax.property).is_none())
+        .filter_map(|element| {
+            let item_type = types_of(element, syntax)
+                .into_iter()
+                .find(|item_type| is_recognized(item_type))?;
+
+            let mut properties = item_object(base, element, syntax);
+            properties.remove("@type");
+
+            Some(Item {
+                source: syntax.source,
+                item_type,
+                properties,
+            })
+        })
+        .collect()
+}
+
+/// Flattens a JSON-LD document into its nodes, including those of an `@graph`.
+fn json_ld_nodes(value: Value, nodes: &mut Vec<Map<String, Value>>) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                json_ld_nodes(value, nodes);
+            }
+        }
+        Value::Object(mut object) => {
+            if let Some(graph) = object.remove("@graph") {
+                json_ld_nodes(graph, nodes);
+            }
+            nodes.push(object);
+        }
+        _ => {}
+    }
+}
+
+fn extract_json_ld(document: &Html) -> Vec<Item> {
+    let selector = Selector::parse("script[type='application/ld+json']").unwrap();
+
+    let mut nodes = vec![];
+
+    for script in document.select(&selector) {
+        let json = script.text().collect::<String>();
+
+        match serde_json::from_str(&json) {
+            Ok(value) => json_ld_nodes(value, &mut nodes),
+            Err(e) => tracing::debug!("Skipping invalid JSON-LD: {}", e),
+        }
+    }
+
+    nodes
+        .into_iter()
+        .filter_map(|mut node| {
+            let types = match node.remove("@type")? {
+                Value::String(item_type) => vec![item_type],
+                Value::Array(types) => types
+                    .into_iter()
+                    .filter_map(|item_type| item_type.as_str().map(str::to_owned))
+                    .collect(),
+                _ => return None,
+            };
+
+            let item_type = types
+                .iter()
+                .map(|item_type| local_name(item_type))
+                .find(|item_type| is_recognized(item_type))?
+                .to_owned();
+
+            node.remove("@context");
+
+            Some(Item {
+                source: "json-ld",
+                item_type,
+                properties: node,
+            })
+        })
+        .collect()
+}
+
+/// Extracts the recognized schema.org items of a document, resolving URLs
+/// against `base`.
+fn extract_structured_data(base: &reqwest::Url, html: &str) -> Vec<Item> {
+    let document = Html::parse_document(html);
+
+    let mut items = extract_json_ld(&document);
+    items.extend(extract_items(base, &document, &MICRODATA));
+    items.extend(extract_items(base, &document, &RDFA));
+
+    items
+}
+
+pub async fn structured_data(Input { url }: Input) -> Result<Vec<Item>, Error> {
+    let client = crate::tool::fetch::http_client()?;
+
+    tracing::info!("Fetching structured data: {}", url);
+
+    let (final_url, html) = crate::tool::fetch::fetch_html(&client, &url).await?;
+
+    Ok(extract_structured_data(&final_url, &html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_microdata_products() {
+        let html = r#"<html><body>
+            <div itemscope itemtype="https://schema.org/Product">
+                <h1 itemprop="name">Espresso Machine</h1>
+                <img itemprop="image" src="/img/espresso.jpg" alt="">
+                <p itemprop="description">A compact machine for two cups at once.</p>
+                <meta itemprop="sku" content="EM-200">
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <span itemprop="price">199.00</span>
+                    <meta itemprop="priceCurrency" content="EUR">
+                    <link itemprop="availability" href="https://schema.org/InStock">
+                </div>
+                <span itemprop="category">Kitchen</span>
+                <span itemprop="category">Coffee</span>
+            </div>
+            <div itemscope itemtype="https://schema.org/Person"><span itemprop="name">Not returned</span></div>
+        </body></html>"#;
+
+        let base = reqwest::Url::parse("https://shop.example.com/espresso").unwrap();
+
+        let items = extract_structured_data(&base, html);
+        assert_eq!(items.len(), 1);
+
+        let product = &items[0];
+        assert_eq!(product.source, "microdata");
+        assert_eq!(product.item_type, "Product");
+        assert_eq!(
+            Value::Object(product.properties.clone()),
+            serde_json::json!({
+                "name": "Espresso Machine",
+                "image": "https://shop.example.com/img/espresso.jpg",
+                "description": "A compact machine for two cups at once.",
+                "sku": "EM-200",
+                "offers": {
+                    "@type": "Offer",
+                    "price": "199.00",
+                    "priceCurrency": "EUR",
+                    "availability": "https://schema.org/InStock",
+                },
+                "category": ["Kitchen", "Coffee"],
+            })
+        );
+    }
+}
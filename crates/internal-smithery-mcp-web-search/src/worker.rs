@@ -0,0 +1,173 @@
+use std::{
+    sync::{Arc, LazyLock, Mutex, mpsc},
+    time::Duration,
+};
+
+use crate::tool::fetch::Error;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed-size thread pool dedicated to blocking browser operations.
+///
+/// Keeping browser work off tokio's general blocking pool bounds the number
+/// of threads driving Chrome, independently of other blocking work.
+pub struct WorkerPool {
+    sender: mpsc::Sender<Job>,
+    /// One slot per worker, so that callers wait here with a timeout rather
+    /// than in the unbounded channel. A slot is held until its job completes,
+    /// even if the caller stopped waiting.
+    slots: Arc<tokio::sync::Semaphore>,
+    queue_timeout: Duration,
+}
+
+/// No worker became free within the queue timeout.
+#[derive(Debug)]
+pub struct BrowserBusy {
+    pub waited: Duration,
+}
+
+impl std::fmt::Display for BrowserBusy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "BrowserBusy: no browser worker became free within {:?}, try again later",
+            self.waited
+        )
+    }
+}
+
+impl std::error::Error for BrowserBusy {}
+
+impl WorkerPool {
+    fn new(threads: usize, queue_timeout: Duration) -> Self {
+        let threads = threads.max(1);
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for index in 0..threads {
+            let receiver = Arc::clone(&receiver);
+
+            std::thread::Builder::new()
+                .name(format!("browser-worker-{index}"))
+                .spawn(move || {
+                    loop {
+                        let job = match receiver.lock() {
+                            Ok(receiver) => receiver.recv(),
+                            Err(_) => break,
+                        };
+
+                        match job {
+                            // A panicking job must not take the worker down with it
+                            Ok(job) => {
+                                let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(job));
+                            }
+                            Err(_) => break,
+                        }
+                    }
+                })
+                .expect("failed to spawn browser worker thread");
+        }
+
+        Self {
+            sender,
+            slots: Arc::new(tokio::sync::Semaphore::new(threads)),
+            queue_timeout,
+        }
+    }
+
+    /// Runs `job` on the pool and waits for its result, failing with
+    /// `BrowserBusy` when no worker frees up in time.
+    pub async fn run<T, F>(&self, job: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let slot =
+            tokio::time::timeout(self.queue_timeout, Arc::clone(&self.slots).acquire_owned())
+                .await
+                .map_err(|_| BrowserBusy {
+                    waited: self.queue_timeout,
+                })?
+                .map_err(|_| "browser worker pool is shut down")?;
+
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+
+        self.sender
+            .send(Box::new(move || {
+                let _slot = slot;
+                let _ = sender.send(job());
+            }))
+            .map_err(|_| "browser worker pool is shut down")?;
+
+        receiver.await.map_err(|_| "browser worker panicked".into())
+    }
+}
+
+static POOL: LazyLock<WorkerPool> = LazyLock::new(|| {
+    let config = crate::config::config();
+    WorkerPool::new(config.browser_worker_threads, config.browser_queue_timeout)
+});
+
+/// Runs `job` on the dedicated browser worker pool.
+pub async fn spawn<T, F>(job: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    POOL.run(job).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fails_as_busy_when_no_worker_frees_up() {
+        let pool = WorkerPool::new(1, Duration::from_millis(50));
+
+        let (slow, queued) = tokio::join!(
+            pool.run(|| std::thread::sleep(Duration::from_millis(500))),
+            pool.run(|| ()),
+        );
+
+        assert!(slow.is_ok());
+
+        let busy = queued.unwrap_err();
+        assert_eq!(
+            busy.downcast_ref::<BrowserBusy>().map(|busy| busy.waited),
+            Some(Duration::from_millis(50))
+        );
+
+        // The worker is free again once the slow job is done
+        assert_eq!(pool.run(|| 42).await.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn survives_panicking_jobs() {
+        let pool = WorkerPool::new(1, Duration::from_secs(1));
+
+        assert!(pool.run(|| panic!("job failed")).await.is_err());
+        assert_eq!(pool.run(|| 42).await.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn runs_jobs_on_the_dedicated_threads() {
+        let thread_name = || std::thread::current().name().map(str::to_owned);
+
+        let pool = WorkerPool::new(2, Duration::from_secs(1));
+        let names = futures::future::join_all((0..4).map(|_| pool.run(thread_name))).await;
+
+        for name in names {
+            let name = name.unwrap().unwrap();
+            assert!(
+                matches!(name.as_str(), "browser-worker-0" | "browser-worker-1"),
+                "{name}"
+            );
+        }
+
+        // The shared pool, as used by the browser
+        let name = spawn(thread_name).await.unwrap().unwrap();
+        assert!(name.starts_with("browser-worker-"), "{name}");
+        assert_ne!(thread_name(), Some(name));
+    }
+}